@@ -0,0 +1,370 @@
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tokio::net::{self, ToSocketAddrs, UdpSocket};
+use tokio::sync::{oneshot, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant};
+
+use crate::authentication::check;
+use crate::authentication::package::{DecodeError, Message, MAX_DATAGRAM_SIZE};
+use crate::authentication::sas;
+use crate::authentication::transaction::{self, Exchange};
+use crate::error::AuthError;
+use crate::failover::{self, Failover};
+use crate::retry::RetryPolicy;
+use crate::socket::SocketOptions;
+use crate::{Gas, Sas};
+
+/// Asynchronous client for the UDP authentication protocol, on tokio.
+///
+/// Offers the same operations as [`Client`](crate::Client). Any number of
+/// calls may run concurrently on one `AsyncClient`: a background task reads
+/// the socket and hands each reply to the call it answers. Dropping a call's
+/// future cancels it cleanly.
+///
+/// Like a `Client`, it fails over to the next server address when the
+/// current one does not answer. Calls in flight finish on the old server
+/// before the socket moves.
+///
+/// Anonymous replies are handled as `pipeline::pipeline` describes for
+/// `Client::transact_all`: the calls they could answer are retried one at a
+/// time, with no other request in flight.
+pub struct AsyncClient {
+    shared: Arc<Shared>,
+    /// Every known server address, in the order they are tried.
+    servers: Vec<SocketAddr>,
+    /// Index in `servers` of the address the socket is connected to.
+    current: AtomicUsize,
+    policy: RetryPolicy,
+    /// Held shared by every call, and exclusively by a call that must be the
+    /// only one in flight.
+    exclusive: RwLock<()>,
+    reader: JoinHandle<()>,
+}
+
+struct Shared {
+    socket: UdpSocket,
+    waiters: Mutex<Vec<Waiter>>,
+    next_waiter: AtomicU64,
+    discarded: AtomicUsize,
+}
+
+/// A call waiting for the reply to `request`.
+struct Waiter {
+    id: u64,
+    request: Message,
+    reply: oneshot::Sender<Delivery>,
+}
+
+/// What the reader task hands to a waiting call.
+enum Delivery {
+    /// A reply, decoded and as received.
+    Reply(Message, Vec<u8>),
+    Malformed(DecodeError),
+    Failed(ErrorKind, String),
+    /// An anonymous reply arrived while other calls were also waiting.
+    Ambiguous,
+}
+
+/// Removes a waiter when its call ends, whether it completed or was dropped.
+struct Registration<'a> {
+    shared: &'a Shared,
+    id: u64,
+}
+
+impl AsyncClient {
+    /// Binds a local socket and connects it to the authentication server,
+    /// failing over between all of its addresses.
+    pub async fn connect<A: ToSocketAddrs>(server: A) -> Result<Self, AuthError> {
+        Self::connect_all([server]).await
+    }
+
+    /// Binds a local socket and connects it to the first address of the
+    /// first server, failing over to the others in order.
+    pub async fn connect_all<A: ToSocketAddrs>(servers: impl IntoIterator<Item = A>) -> Result<Self, AuthError> {
+        Self::connect_with(servers, &SocketOptions::default()).await
+    }
+
+    /// Like [`AsyncClient::connect_all`], with the local socket set up per
+    /// `options`, and the server addresses ordered as
+    /// [`Client::connect_with`](crate::Client::connect_with) orders them.
+    pub async fn connect_with<A: ToSocketAddrs>(
+        servers: impl IntoIterator<Item = A>,
+        options: &SocketOptions,
+    ) -> Result<Self, AuthError> {
+        let mut resolved = Vec::new();
+        for server in servers {
+            resolved.push(net::lookup_host(server).await.map(Iterator::collect));
+        }
+
+        let mut addresses = failover::addresses(resolved)?;
+        let socket = options.bind(&mut addresses)?;
+
+        socket.set_nonblocking(true)?;
+        let socket = UdpSocket::from_std(socket)?;
+        socket.connect(addresses[0]).await?;
+
+        Ok(Self::with_servers(socket, addresses))
+    }
+
+    /// Wraps an already connected socket.
+    ///
+    /// Must be called from within a tokio runtime, which runs the task that
+    /// reads replies.
+    pub fn new(socket: UdpSocket) -> Self {
+        let servers = socket.peer_addr().into_iter().collect();
+        Self::with_servers(socket, servers)
+    }
+
+    fn with_servers(socket: UdpSocket, servers: Vec<SocketAddr>) -> Self {
+        let shared = Arc::new(Shared {
+            socket,
+            waiters: Mutex::new(Vec::new()),
+            next_waiter: AtomicU64::new(0),
+            discarded: AtomicUsize::new(0),
+        });
+
+        Self {
+            reader: tokio::spawn(read_replies(Arc::clone(&shared))),
+            shared,
+            servers,
+            current: AtomicUsize::new(0),
+            policy: RetryPolicy::default(),
+            exclusive: RwLock::new(()),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    pub fn socket(&self) -> &UdpSocket {
+        &self.shared.socket
+    }
+
+    /// Every server address the client may fail over to, in order.
+    pub fn servers(&self) -> &[SocketAddr] {
+        &self.servers
+    }
+
+    /// Number of datagrams dropped so far because they did not answer any
+    /// call in flight.
+    pub fn discarded_datagrams(&self) -> usize {
+        self.shared.discarded.load(Ordering::Relaxed)
+    }
+
+    /// Sends any request and returns the reply that answers it. Group
+    /// messages with more members than fit in a datagram are rejected.
+    pub async fn transact(&self, request: &Message) -> Result<Exchange, AuthError> {
+        check::member_count(request)?;
+
+        let mut failover = Failover::start(&self.policy, self.servers.len());
+        let mut server = self.current.load(Ordering::Relaxed);
+        let mut result = Err(AuthError::Timeout { attempts: 0 });
+
+        while let Some((offset, policy)) = failover.next_server() {
+            if offset > 0 {
+                server = self.fail_over(server).await?;
+            }
+
+            result = self.transact_on(request, policy).await;
+
+            if !failover.unanswered(&result) {
+                return result;
+            }
+        }
+
+        failover.finish(result)
+    }
+
+    /// Moves the socket from the server at index `from` to the next one,
+    /// unless a concurrent call already moved it, and returns the index of
+    /// the server it is now connected to.
+    async fn fail_over(&self, from: usize) -> Result<usize, AuthError> {
+        let _exclusive = self.exclusive.write().await;
+
+        if self.current.load(Ordering::Relaxed) == from {
+            let index = (from + 1) % self.servers.len();
+            self.shared.socket.connect(self.servers[index]).await?;
+            self.current.store(index, Ordering::Relaxed);
+        }
+
+        Ok(self.current.load(Ordering::Relaxed))
+    }
+
+    /// Sends `request` to the current server.
+    async fn transact_on(&self, request: &Message, policy: &RetryPolicy) -> Result<Exchange, AuthError> {
+        {
+            let _shared = self.exclusive.read().await;
+
+            if let Some(exchange) = self.attempt(request, policy).await? {
+                return Ok(exchange);
+            }
+        }
+
+        let _exclusive = self.exclusive.write().await;
+
+        self.attempt(request, policy)
+            .await?
+            .ok_or_else(|| Error::other("Reply could not be matched to its request!").into())
+    }
+
+    /// Requests an individual token (SAS) for `id` and `nonce`.
+    pub async fn request_individual_token(&self, id: &str, nonce: u32) -> Result<Sas, AuthError> {
+        let request = Message::IndividualTokenRequest {
+            id: sas::parse_id(id)?,
+            nonce,
+        };
+
+        check::individual_token(self.transact(&request).await?.reply)
+    }
+
+    /// Asks the server whether `sas` is valid.
+    pub async fn validate_individual_token(&self, sas: &Sas) -> Result<bool, AuthError> {
+        let request = Message::IndividualTokenValidation(sas.clone());
+
+        check::individual_status(self.transact(&request).await?.reply)
+    }
+
+    /// Requests a group token (GAS) covering every SAS in `members`.
+    pub async fn request_group_token(&self, members: &[Sas]) -> Result<Gas, AuthError> {
+        let request = Message::GroupTokenRequest(members.to_vec());
+
+        check::group_token(self.transact(&request).await?.reply)
+    }
+
+    /// Asks the server whether `gas` is valid.
+    pub async fn validate_group_token(&self, gas: &Gas) -> Result<bool, AuthError> {
+        let request = Message::GroupTokenValidation(gas.clone());
+
+        check::group_status(self.transact(&request).await?.reply)
+    }
+
+    /// Sends `request` under the retry policy until it is answered.
+    ///
+    /// Returns `None` if an anonymous reply could have been meant for this
+    /// call or another one.
+    async fn attempt(&self, request: &Message, policy: &RetryPolicy) -> Result<Option<Exchange>, AuthError> {
+        let (sender, mut receiver) = oneshot::channel();
+        let _registration = self.shared.register(request.clone(), sender);
+
+        let datagram = request.encode();
+        let mut schedule = policy.start();
+
+        while let Some(timeout) = schedule.next_timeout() {
+            self.shared.socket.send(&datagram).await?;
+            let sent = Instant::now();
+
+            let delivery = match time::timeout(timeout, &mut receiver).await {
+                Ok(Ok(delivery)) => delivery,
+                Ok(Err(_)) => return Err(Error::from(ErrorKind::BrokenPipe).into()),
+                Err(_) => continue,
+            };
+
+            return match delivery {
+                Delivery::Reply(reply, received) => Ok(Some(Exchange {
+                    reply,
+                    sent: datagram,
+                    received,
+                    server: transaction::canonical(self.shared.socket.peer_addr()?),
+                    attempts: schedule.attempts(),
+                    rtt: sent.elapsed(),
+                    discarded: 0,
+                })),
+                Delivery::Malformed(e) => Err(e.into()),
+                Delivery::Failed(kind, message) => Err(Error::new(kind, message).into()),
+                Delivery::Ambiguous => Ok(None),
+            };
+        }
+
+        Err(AuthError::Timeout {
+            attempts: schedule.attempts(),
+        })
+    }
+}
+
+impl Drop for AsyncClient {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+impl Shared {
+    fn waiters(&self) -> MutexGuard<'_, Vec<Waiter>> {
+        self.waiters.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn register(&self, request: Message, reply: oneshot::Sender<Delivery>) -> Registration<'_> {
+        let id = self.next_waiter.fetch_add(1, Ordering::Relaxed);
+        self.waiters().push(Waiter { id, request, reply });
+
+        Registration { shared: self, id }
+    }
+
+    /// Hands a received datagram, decoded and as received, to the waiter it
+    /// answers.
+    fn deliver(&self, datagram: Result<Message, DecodeError>, bytes: &[u8]) {
+        let mut waiters = self.waiters();
+
+        let anonymous = matches!(datagram, Ok(Message::ErrorMessage(_)) | Err(_));
+        if anonymous && waiters.len() > 1 {
+            for waiter in waiters.drain(..) {
+                let _ = waiter.reply.send(Delivery::Ambiguous);
+            }
+            return;
+        }
+
+        let position = match &datagram {
+            Ok(reply) => waiters.iter().position(|waiter| reply.is_reply_to(&waiter.request)),
+            Err(_) => (!waiters.is_empty()).then_some(0),
+        };
+
+        let Some(position) = position else {
+            self.discarded.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+
+        let waiter = waiters.swap_remove(position);
+        let delivery = match datagram {
+            Ok(reply) => Delivery::Reply(reply, bytes.to_vec()),
+            Err(e) => Delivery::Malformed(e),
+        };
+        let _ = waiter.reply.send(delivery);
+    }
+
+    /// Reports a transport failure to every waiter.
+    fn fail(&self, error: &Error) {
+        for waiter in self.waiters().drain(..) {
+            let _ = waiter.reply.send(Delivery::Failed(error.kind(), error.to_string()));
+        }
+    }
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        self.shared.waiters().retain(|waiter| waiter.id != self.id);
+    }
+}
+
+/// Reads the socket for as long as the client lives.
+async fn read_replies(shared: Arc<Shared>) {
+    let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+
+    loop {
+        match shared.socket.recv(&mut buf).await {
+            Ok(received) if received == buf.len() => {
+                shared.deliver(Err(DecodeError::Truncated { capacity: buf.len() }), &buf);
+            }
+            Ok(received) => shared.deliver(Message::decode(&buf[..received]), &buf[..received]),
+            Err(e) => shared.fail(&e),
+        }
+    }
+}
@@ -1,3 +1,10 @@
+use std::fmt;
+
+use super::package::{Message, MAX_MEMBERS};
+use crate::error::AuthError;
+use crate::{Gas, Sas};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TokenType {
     IndividualTokenRequest = 1,
     IndividualTokenResponse = 2,
@@ -10,88 +17,144 @@ pub enum TokenType {
     ErrorMessage = 256,
 }
 
-pub enum ErrorMessage {
-    InvalidMessageCode = 1,
-    IncorrectMessageLength = 2,
-    InvalidParameter = 3,
-    InvalidSingleToken = 4,
-    AsciiDecodeError = 5,
+/// Error code carried by a type 256 message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    InvalidMessageCode,
+    IncorrectMessageLength,
+    InvalidParameter,
+    InvalidSingleToken,
+    AsciiDecodeError,
+    /// A code this client does not know about.
+    Unknown(u16),
 }
 
-const ERROR_MESSAGES: [&str; 5] = [
-    "Error: Invalid message code!",
-    "Error: Incorrect message length!",
-    "Error: Invalid parameter!",
-    "Error: Invalid single token!",
-    "Error: ASCII decode error!",
-];
-
-fn check_error_code(token_type: u16, error_message: u16) {
-    if token_type == TokenType::ErrorMessage as u16 {
-        if let Some(&message) = match error_message {
-            x if x == ErrorMessage::InvalidMessageCode as u16 => Some(&ERROR_MESSAGES[0]),
-            x if x == ErrorMessage::IncorrectMessageLength as u16 => Some(&ERROR_MESSAGES[1]),
-            x if x == ErrorMessage::InvalidParameter as u16 => Some(&ERROR_MESSAGES[2]),
-            x if x == ErrorMessage::InvalidSingleToken as u16 => Some(&ERROR_MESSAGES[3]),
-            x if x == ErrorMessage::AsciiDecodeError as u16 => Some(&ERROR_MESSAGES[4]),
+/// Status byte of a type 4 or type 8 message for a valid token.
+pub const VALID_STATUS: u8 = 0;
+/// Status byte of a type 4 or type 8 message for an invalid token.
+pub const INVALID_STATUS: u8 = 1;
+
+impl TokenType {
+    pub fn from_code(code: u16) -> Option<Self> {
+        match code {
+            x if x == Self::IndividualTokenRequest as u16 => Some(Self::IndividualTokenRequest),
+            x if x == Self::IndividualTokenResponse as u16 => Some(Self::IndividualTokenResponse),
+            x if x == Self::IndividualTokenValidation as u16 => Some(Self::IndividualTokenValidation),
+            x if x == Self::IndividualTokenStatus as u16 => Some(Self::IndividualTokenStatus),
+            x if x == Self::GroupTokenRequest as u16 => Some(Self::GroupTokenRequest),
+            x if x == Self::GroupTokenResponse as u16 => Some(Self::GroupTokenResponse),
+            x if x == Self::GroupTokenValidation as u16 => Some(Self::GroupTokenValidation),
+            x if x == Self::GroupTokenStatus as u16 => Some(Self::GroupTokenStatus),
+            x if x == Self::ErrorMessage as u16 => Some(Self::ErrorMessage),
             _ => None,
-        } {
-            eprintln!("{}", message);
-            std::process::exit(1);
         }
     }
 }
 
-fn extract_token_info(buf: &[u8]) -> (u16, u16) {
-    let token_type = u16::from_be_bytes(buf[..2].try_into().unwrap());
-    let error_message = u16::from_be_bytes(buf[2..4].try_into().unwrap());
-    (token_type, error_message)
-}
+impl ErrorCode {
+    pub fn from_code(code: u16) -> Self {
+        match code {
+            1 => Self::InvalidMessageCode,
+            2 => Self::IncorrectMessageLength,
+            3 => Self::InvalidParameter,
+            4 => Self::InvalidSingleToken,
+            5 => Self::AsciiDecodeError,
+            other => Self::Unknown(other),
+        }
+    }
 
-pub fn check_sas_request(buf: &[u8]) {
-    let (token_type, error_message) = extract_token_info(buf);
-    check_error_code(token_type, error_message);
-    assert!(token_type == TokenType::IndividualTokenRequest as u16, "Invalid SAS request token type!");
+    pub fn code(&self) -> u16 {
+        match self {
+            Self::InvalidMessageCode => 1,
+            Self::IncorrectMessageLength => 2,
+            Self::InvalidParameter => 3,
+            Self::InvalidSingleToken => 4,
+            Self::AsciiDecodeError => 5,
+            Self::Unknown(code) => *code,
+        }
+    }
+
+    /// What the server is telling us, in plain words.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::InvalidMessageCode => "Unknown request type.",
+            Self::IncorrectMessageLength => "Incompatible message size.",
+            Self::InvalidParameter => "Invalid field value.",
+            Self::InvalidSingleToken => "Invalid SAS in a GAS.",
+            Self::AsciiDecodeError => "Non-ASCII character detected.",
+            Self::Unknown(_) => "The server reported an error this client does not know.",
+        }
+    }
 }
 
-pub fn check_sas_response(buf: &[u8]) {
-    let (token_type, error_message) = extract_token_info(buf);
-    check_error_code(token_type, error_message);
-    assert!(token_type == TokenType::IndividualTokenResponse as u16, "Invalid SAS response token type!");
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidMessageCode => write!(f, "Invalid message code!"),
+            Self::IncorrectMessageLength => write!(f, "Incorrect message length!"),
+            Self::InvalidParameter => write!(f, "Invalid parameter!"),
+            Self::InvalidSingleToken => write!(f, "Invalid single token!"),
+            Self::AsciiDecodeError => write!(f, "ASCII decode error!"),
+            Self::Unknown(code) => write!(f, "Unknown error code {code}!"),
+        }
+    }
 }
 
-pub fn check_sas_status(buf: &[u8]) {
-    let (token_type, error_message) = extract_token_info(buf);
-    check_error_code(token_type, error_message);
-    assert!(token_type == TokenType::IndividualTokenStatus as u16, "Invalid SAS status token type!");
+/// Rejects a group message with more members than fit in a datagram.
+pub(crate) fn member_count(request: &Message) -> Result<(), AuthError> {
+    let count = match request {
+        Message::GroupTokenRequest(members) => members.len(),
+        Message::GroupTokenValidation(gas) => gas.members.len(),
+        _ => return Ok(()),
+    };
+
+    if count > MAX_MEMBERS {
+        return Err(AuthError::InvalidInput(format!(
+            "Too many members! At most {MAX_MEMBERS} fit in a datagram, got {count}."
+        )));
+    }
+    Ok(())
 }
 
-pub fn check_sas_validation(buf: &[u8]) {
-    let (token_type, error_message) = extract_token_info(buf);
-    check_error_code(token_type, error_message);
-    assert!(token_type == TokenType::IndividualTokenValidation as u16, "Invalid SAS validation token type!");
+/// Turns a reply that is not the expected response into the matching error.
+pub(crate) fn unexpected_reply(reply: Message, expected: TokenType) -> AuthError {
+    match reply {
+        Message::ErrorMessage(error) => AuthError::Server(error),
+        other => AuthError::UnexpectedMessage {
+            expected,
+            found: other.token_type(),
+        },
+    }
 }
 
-pub fn check_gas_request(buf: &[u8]) {
-    let (token_type, error_message) = extract_token_info(buf);
-    check_error_code(token_type, error_message);
-    assert!(token_type == TokenType::GroupTokenRequest as u16, "Invalid GAS request token type!");
+/// Extracts the SAS from the reply to an individual token request.
+pub(crate) fn individual_token(reply: Message) -> Result<Sas, AuthError> {
+    match reply {
+        Message::IndividualTokenResponse(sas) => Ok(sas),
+        other => Err(unexpected_reply(other, TokenType::IndividualTokenResponse)),
+    }
 }
 
-pub fn check_gas_response(buf: &[u8]) {
-    let (token_type, error_message) = extract_token_info(buf);
-    check_error_code(token_type, error_message);
-    assert!(token_type == TokenType::GroupTokenResponse as u16, "Invalid GAS response token type!");
+/// Extracts the validity from the reply to an individual token validation.
+pub(crate) fn individual_status(reply: Message) -> Result<bool, AuthError> {
+    match reply {
+        Message::IndividualTokenStatus { status, .. } => Ok(status == VALID_STATUS),
+        other => Err(unexpected_reply(other, TokenType::IndividualTokenStatus)),
+    }
 }
 
-pub fn check_gas_status(buf: &[u8]) {
-    let (token_type, error_message) = extract_token_info(buf);
-    check_error_code(token_type, error_message);
-    assert!(token_type == TokenType::GroupTokenStatus as u16, "Invalid GAS status token type!");
+/// Extracts the GAS from the reply to a group token request.
+pub(crate) fn group_token(reply: Message) -> Result<Gas, AuthError> {
+    match reply {
+        Message::GroupTokenResponse(gas) => Ok(gas),
+        other => Err(unexpected_reply(other, TokenType::GroupTokenResponse)),
+    }
 }
 
-pub fn check_gas_validation(buf: &[u8]) {
-    let (token_type, error_message) = extract_token_info(buf);
-    check_error_code(token_type, error_message);
-    assert!(token_type == TokenType::GroupTokenValidation as u16, "Invalid GAS validation token type!");
+/// Extracts the validity from the reply to a group token validation.
+pub(crate) fn group_status(reply: Message) -> Result<bool, AuthError> {
+    match reply {
+        Message::GroupTokenStatus { status, .. } => Ok(status == VALID_STATUS),
+        other => Err(unexpected_reply(other, TokenType::GroupTokenStatus)),
+    }
 }
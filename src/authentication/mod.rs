@@ -1,4 +1,6 @@
-mod check;
+pub mod check;
 pub mod gas;
-mod package;
+pub mod package;
+pub(crate) mod pipeline;
 pub mod sas;
+pub mod transaction;
@@ -0,0 +1,291 @@
+use thiserror::Error;
+
+use crate::authentication::check::{ErrorCode, TokenType};
+use crate::authentication::gas::Gas;
+use crate::authentication::sas::{Sas, ID_LEN, TOKEN_LEN};
+
+/// Receive buffer size that fits any UDP datagram.
+pub const MAX_DATAGRAM_SIZE: usize = 65_535;
+
+/// Largest UDP payload that can be sent, over IPv4.
+const MAX_UDP_PAYLOAD: usize = 65_507;
+
+const SIZE_TYPE_LEN: usize = 2;
+const SIZE_COUNT_LEN: usize = 2;
+const SIZE_NONCE_LEN: usize = 4;
+const SIZE_STATUS_LEN: usize = 1;
+const SIZE_SAS_LEN: usize = ID_LEN + SIZE_NONCE_LEN + TOKEN_LEN;
+const PACK_HEAD_SIZE: usize = SIZE_TYPE_LEN + SIZE_COUNT_LEN;
+
+/// Most members a group message can carry, so that the group token status
+/// answering it still fits in one datagram.
+pub const MAX_MEMBERS: usize = (MAX_UDP_PAYLOAD - message_len(TokenType::GroupTokenStatus, 0)) / SIZE_SAS_LEN;
+
+/// Every message of the authentication protocol.
+///
+/// The same codec serves both directions, so clients, servers and test
+/// tools all agree on the wire format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// Type 1: asks for a SAS for `id` and `nonce`.
+    IndividualTokenRequest { id: [u8; ID_LEN], nonce: u32 },
+    /// Type 2: the SAS issued for a type 1 request.
+    IndividualTokenResponse(Sas),
+    /// Type 3: asks whether a SAS is valid.
+    IndividualTokenValidation(Sas),
+    /// Type 4: the echoed SAS and its status, `0` when valid.
+    IndividualTokenStatus { sas: Sas, status: u8 },
+    /// Type 5: asks for a GAS covering the listed SAS.
+    GroupTokenRequest(Vec<Sas>),
+    /// Type 6: the GAS issued for a type 5 request.
+    GroupTokenResponse(Gas),
+    /// Type 7: asks whether a GAS is valid.
+    GroupTokenValidation(Gas),
+    /// Type 8: the echoed GAS and its status, `0` when valid.
+    GroupTokenStatus { gas: Gas, status: u8 },
+    /// Type 256: the server rejected the request.
+    ErrorMessage(ErrorCode),
+}
+
+/// Why a datagram could not be decoded into a [`Message`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DecodeError {
+    #[error("Incorrect message length! Expected {expected} bytes, got {found}.")]
+    IncorrectMessageLength { expected: usize, found: usize },
+
+    #[error("Datagram filled the {capacity}-byte receive buffer and may have been truncated!")]
+    Truncated { capacity: usize },
+
+    #[error("ASCII decode error! An ID or token contains a non-ASCII byte.")]
+    AsciiDecodeError,
+
+    #[error("Invalid message code {0}!")]
+    InvalidMessageCode(u16),
+}
+
+impl Message {
+    pub fn token_type(&self) -> TokenType {
+        match self {
+            Self::IndividualTokenRequest { .. } => TokenType::IndividualTokenRequest,
+            Self::IndividualTokenResponse(_) => TokenType::IndividualTokenResponse,
+            Self::IndividualTokenValidation(_) => TokenType::IndividualTokenValidation,
+            Self::IndividualTokenStatus { .. } => TokenType::IndividualTokenStatus,
+            Self::GroupTokenRequest(_) => TokenType::GroupTokenRequest,
+            Self::GroupTokenResponse(_) => TokenType::GroupTokenResponse,
+            Self::GroupTokenValidation(_) => TokenType::GroupTokenValidation,
+            Self::GroupTokenStatus { .. } => TokenType::GroupTokenStatus,
+            Self::ErrorMessage(_) => TokenType::ErrorMessage,
+        }
+    }
+
+    /// Whether this message answers `request`.
+    ///
+    /// Replies echo the identifying fields of their request, so a late reply
+    /// to an earlier attempt or to a different request can be told apart.
+    /// Error messages carry nothing to compare and always match.
+    pub fn is_reply_to(&self, request: &Message) -> bool {
+        match (request, self) {
+            (Self::IndividualTokenRequest { id, nonce }, Self::IndividualTokenResponse(sas)) => {
+                sas.id == *id && sas.nonce == *nonce
+            }
+            (Self::IndividualTokenValidation(sent), Self::IndividualTokenStatus { sas, .. }) => sas == sent,
+            (Self::GroupTokenRequest(members), Self::GroupTokenResponse(gas)) => gas.members == *members,
+            (Self::GroupTokenValidation(sent), Self::GroupTokenStatus { gas, .. }) => gas == sent,
+            (_, Self::ErrorMessage(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Serializes the message in network byte order.
+    ///
+    /// Group messages carry their member count in a 2-byte field. Messages
+    /// with more than [`MAX_MEMBERS`] members do not fit in a datagram, and
+    /// the clients reject them before encoding.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(&(self.token_type() as u16).to_be_bytes());
+
+        match self {
+            Self::IndividualTokenRequest { id, nonce } => {
+                buffer.extend_from_slice(id);
+                buffer.extend_from_slice(&nonce.to_be_bytes());
+            }
+            Self::IndividualTokenResponse(sas) | Self::IndividualTokenValidation(sas) => {
+                add_sas_to_buffer(&mut buffer, sas);
+            }
+            Self::IndividualTokenStatus { sas, status } => {
+                add_sas_to_buffer(&mut buffer, sas);
+                buffer.push(*status);
+            }
+            Self::GroupTokenRequest(members) => {
+                add_members_to_buffer(&mut buffer, members);
+            }
+            Self::GroupTokenResponse(gas) | Self::GroupTokenValidation(gas) => {
+                add_gas_to_buffer(&mut buffer, gas);
+            }
+            Self::GroupTokenStatus { gas, status } => {
+                add_gas_to_buffer(&mut buffer, gas);
+                buffer.push(*status);
+            }
+            Self::ErrorMessage(error) => {
+                buffer.extend_from_slice(&error.code().to_be_bytes());
+            }
+        }
+
+        buffer
+    }
+
+    /// Parses a datagram into a message.
+    ///
+    /// The datagram must have exactly the length its type (and, for group
+    /// messages, its N field) calls for, and every ID and token must be ASCII.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes);
+        let code = reader.u16()?;
+        let token_type = TokenType::from_code(code).ok_or(DecodeError::InvalidMessageCode(code))?;
+
+        let expected = message_len(token_type, reader.count(token_type)?);
+        if bytes.len() != expected {
+            return Err(DecodeError::IncorrectMessageLength {
+                expected,
+                found: bytes.len(),
+            });
+        }
+
+        let message = match token_type {
+            TokenType::IndividualTokenRequest => Self::IndividualTokenRequest {
+                id: reader.ascii()?,
+                nonce: reader.u32()?,
+            },
+            TokenType::IndividualTokenResponse => Self::IndividualTokenResponse(reader.sas()?),
+            TokenType::IndividualTokenValidation => Self::IndividualTokenValidation(reader.sas()?),
+            TokenType::IndividualTokenStatus => Self::IndividualTokenStatus {
+                sas: reader.sas()?,
+                status: reader.u8()?,
+            },
+            TokenType::GroupTokenRequest => Self::GroupTokenRequest(reader.members()?),
+            TokenType::GroupTokenResponse => Self::GroupTokenResponse(reader.gas()?),
+            TokenType::GroupTokenValidation => Self::GroupTokenValidation(reader.gas()?),
+            TokenType::GroupTokenStatus => Self::GroupTokenStatus {
+                gas: reader.gas()?,
+                status: reader.u8()?,
+            },
+            TokenType::ErrorMessage => Self::ErrorMessage(ErrorCode::from_code(reader.u16()?)),
+        };
+
+        Ok(message)
+    }
+}
+
+/// Exact size of a message of `token_type` carrying `count` SAS.
+///
+/// `count` is ignored for individual and error messages.
+pub const fn message_len(token_type: TokenType, count: usize) -> usize {
+    let members_len = PACK_HEAD_SIZE + SIZE_SAS_LEN * count;
+
+    match token_type {
+        TokenType::IndividualTokenRequest => SIZE_TYPE_LEN + ID_LEN + SIZE_NONCE_LEN,
+        TokenType::IndividualTokenResponse | TokenType::IndividualTokenValidation => SIZE_TYPE_LEN + SIZE_SAS_LEN,
+        TokenType::IndividualTokenStatus => SIZE_TYPE_LEN + SIZE_SAS_LEN + SIZE_STATUS_LEN,
+        TokenType::GroupTokenRequest => members_len,
+        TokenType::GroupTokenResponse | TokenType::GroupTokenValidation => members_len + TOKEN_LEN,
+        TokenType::GroupTokenStatus => members_len + TOKEN_LEN + SIZE_STATUS_LEN,
+        TokenType::ErrorMessage => PACK_HEAD_SIZE,
+    }
+}
+
+fn add_sas_to_buffer(buf: &mut Vec<u8>, sas: &Sas) {
+    buf.extend_from_slice(&sas.id);
+    buf.extend_from_slice(&sas.nonce.to_be_bytes());
+    buf.extend_from_slice(&sas.token);
+}
+
+fn add_members_to_buffer(buf: &mut Vec<u8>, members: &[Sas]) {
+    buf.extend_from_slice(&(members.len() as u16).to_be_bytes());
+    members.iter().for_each(|sas| add_sas_to_buffer(buf, sas));
+}
+
+fn add_gas_to_buffer(buf: &mut Vec<u8>, gas: &Gas) {
+    add_members_to_buffer(buf, &gas.members);
+    buf.extend_from_slice(&gas.token);
+}
+
+/// Cursor over a received datagram that never reads past its end.
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let end = self.pos + len;
+        let slice = self.buf.get(self.pos..end).ok_or(DecodeError::IncorrectMessageLength {
+            expected: end,
+            found: self.buf.len(),
+        })?;
+
+        self.pos = end;
+        Ok(slice)
+    }
+
+    /// Reads the N field of group messages without consuming it.
+    fn count(&self, token_type: TokenType) -> Result<usize, DecodeError> {
+        match token_type {
+            TokenType::GroupTokenRequest
+            | TokenType::GroupTokenResponse
+            | TokenType::GroupTokenValidation
+            | TokenType::GroupTokenStatus => Ok(Reader { buf: self.buf, pos: self.pos }.u16()? as usize),
+            _ => Ok(0),
+        }
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn ascii<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let field = self.array()?;
+
+        if !field.is_ascii() {
+            return Err(DecodeError::AsciiDecodeError);
+        }
+
+        Ok(field)
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(u8::from_be_bytes(self.array()?))
+    }
+
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+
+    fn sas(&mut self) -> Result<Sas, DecodeError> {
+        Ok(Sas {
+            id: self.ascii()?,
+            nonce: self.u32()?,
+            token: self.ascii()?,
+        })
+    }
+
+    fn members(&mut self) -> Result<Vec<Sas>, DecodeError> {
+        let count = self.u16()? as usize;
+        (0..count).map(|_| self.sas()).collect()
+    }
+
+    fn gas(&mut self) -> Result<Gas, DecodeError> {
+        Ok(Gas {
+            members: self.members()?,
+            token: self.ascii()?,
+        })
+    }
+}
@@ -1,2 +1,3 @@
-pub mod gas;
-pub mod sas;
\ No newline at end of file
+mod message;
+
+pub use message::{message_len, DecodeError, Message, MAX_DATAGRAM_SIZE, MAX_MEMBERS};
@@ -0,0 +1,239 @@
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use super::package::{DecodeError, Message, MAX_DATAGRAM_SIZE};
+use super::transaction::{self, Exchange};
+use crate::error::AuthError;
+use crate::retry::{self, RetryPolicy, Schedule};
+
+/// A request that has been sent and is waiting for its reply.
+struct InFlight<'a> {
+    index: usize,
+    datagram: Vec<u8>,
+    schedule: Schedule<'a>,
+    sent: Instant,
+    expires: Instant,
+}
+
+/// Sends every request in `requests` with up to `window` of them in flight at
+/// once, and returns their results in the same order.
+///
+/// Replies are matched to requests with [`Message::is_reply_to`], and each
+/// request is retransmitted on its own schedule under `policy`.
+///
+/// Error messages and malformed datagrams do not say which request they
+/// answer. When a single request is in flight it gets them; otherwise every
+/// request in flight is set aside and, unless a matching reply still shows
+/// up, replayed on its own with [`transaction::transact`] once the replies
+/// still owed have arrived or `policy.initial_timeout` has passed. Datagrams
+/// that answer nothing are counted in `discarded`.
+pub(crate) fn pipeline(
+    socket: &UdpSocket,
+    requests: &[Message],
+    window: usize,
+    policy: &RetryPolicy,
+    discarded: &mut usize,
+) -> Vec<Result<Exchange, AuthError>> {
+    let mut results: Vec<Option<Result<Exchange, AuthError>>> = requests.iter().map(|_| None).collect();
+    let mut queue: VecDeque<usize> = (0..requests.len()).collect();
+    let mut in_flight: Vec<InFlight> = Vec::new();
+    let mut set_aside: Vec<InFlight> = Vec::new();
+    let mut last_error = Error::from(ErrorKind::TimedOut);
+    let (mut sent, mut received) = (0, 0);
+    let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+
+    let server = match socket.peer_addr() {
+        Ok(server) => transaction::canonical(server),
+        Err(e) => return requests.iter().map(|_| Err(Error::new(e.kind(), e.to_string()).into())).collect(),
+    };
+
+    loop {
+        while in_flight.len() < window.max(1) {
+            let Some(index) = queue.pop_front() else {
+                break;
+            };
+
+            let mut request = InFlight {
+                index,
+                datagram: requests[index].encode(),
+                schedule: policy.start(),
+                sent: Instant::now(),
+                expires: Instant::now(),
+            };
+
+            match send(socket, &mut request) {
+                Ok(true) => {
+                    sent += 1;
+                    in_flight.push(request);
+                }
+                Ok(false) => results[index] = Some(Err(AuthError::Timeout { attempts: 0 })),
+                Err(e) => results[index] = Some(Err(e.into())),
+            }
+        }
+
+        let Some(expires) = in_flight.iter().map(|request| request.expires).min() else {
+            break;
+        };
+
+        // An anonymous reply can only be pinned on a request when nothing
+        // else could have caused it.
+        let unambiguous = in_flight.len() == 1 && set_aside.is_empty();
+
+        let (datagram, bytes) = match recv(socket, &mut buf, expires) {
+            Ok((datagram, bytes)) => (Ok(datagram), bytes),
+            Err(e) => (Err(e), Vec::new()),
+        };
+        if datagram.is_ok() {
+            received += 1;
+        }
+
+        match datagram {
+            Ok(Ok(Message::ErrorMessage(error))) if unambiguous => {
+                let request = in_flight.remove(0);
+                results[request.index] = Some(Ok(exchange(&request, Message::ErrorMessage(error), bytes, server)));
+            }
+            Ok(Ok(Message::ErrorMessage(_))) => set_aside.append(&mut in_flight),
+            Ok(Ok(reply)) => {
+                let answered = |request: &InFlight| reply.is_reply_to(&requests[request.index]);
+
+                if let Some(position) = in_flight.iter().position(answered) {
+                    let request = in_flight.swap_remove(position);
+                    results[request.index] = Some(Ok(exchange(&request, reply, bytes, server)));
+                } else if let Some(position) = set_aside.iter().position(answered) {
+                    let request = set_aside.swap_remove(position);
+                    results[request.index] = Some(Ok(exchange(&request, reply, bytes, server)));
+                } else {
+                    *discarded += 1;
+                }
+            }
+            Ok(Err(e)) if unambiguous => {
+                let request = in_flight.remove(0);
+                results[request.index] = Some(Err(e.into()));
+            }
+            Ok(Err(_)) => set_aside.append(&mut in_flight),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) => {
+                last_error = e;
+                in_flight.iter_mut().for_each(|request| request.expires = Instant::now());
+            }
+        }
+
+        let now = Instant::now();
+        let mut index = 0;
+
+        while index < in_flight.len() {
+            if in_flight[index].expires > now {
+                index += 1;
+                continue;
+            }
+
+            match send(socket, &mut in_flight[index]) {
+                Ok(true) => {
+                    sent += 1;
+                    index += 1;
+                }
+                Ok(false) => {
+                    let request = in_flight.swap_remove(index);
+                    results[request.index] = Some(Err(expired(&request, &last_error)));
+                }
+                Err(e) => {
+                    let request = in_flight.swap_remove(index);
+                    results[request.index] = Some(Err(e.into()));
+                }
+            }
+        }
+    }
+
+    // Replies to the requests set aside may still be on their way. Collect
+    // them first, so that none is taken for an answer to a replay.
+    let drained = retry::later(Instant::now(), policy.initial_timeout);
+
+    while !set_aside.is_empty() && received < sent {
+        let Ok((datagram, bytes)) = recv(socket, &mut buf, drained) else {
+            break;
+        };
+        received += 1;
+
+        let answered = |request: &InFlight| match &datagram {
+            Ok(Message::ErrorMessage(_)) | Err(_) => false,
+            Ok(reply) => reply.is_reply_to(&requests[request.index]),
+        };
+
+        if let Some(position) = set_aside.iter().position(answered) {
+            let request = set_aside.swap_remove(position);
+            let reply = datagram.expect("only decoded replies answer a request");
+            results[request.index] = Some(Ok(exchange(&request, reply, bytes, server)));
+        } else {
+            *discarded += 1;
+        }
+    }
+
+    for request in set_aside {
+        let result = transaction::transact(socket, &requests[request.index], policy, &mut None);
+
+        if let Ok(exchange) = &result {
+            *discarded += exchange.discarded;
+        }
+        results[request.index] = Some(result);
+    }
+
+    results.into_iter().map(|result| result.expect("every request gets a result")).collect()
+}
+
+/// Transmits `request` for its next attempt, or returns `false` once its
+/// schedule has run out.
+fn send(socket: &UdpSocket, request: &mut InFlight) -> Result<bool, Error> {
+    let Some(timeout) = request.schedule.next_timeout() else {
+        return Ok(false);
+    };
+
+    socket.send(&request.datagram)?;
+    request.sent = Instant::now();
+    request.expires = retry::later(request.sent, timeout);
+
+    Ok(true)
+}
+
+/// Receives one datagram, waiting no later than `expires`, and returns it
+/// decoded and as received.
+///
+/// The outer error is a transport failure or timeout; the inner one is a
+/// datagram that arrived but could not be decoded.
+fn recv(
+    socket: &UdpSocket,
+    buf: &mut [u8],
+    expires: Instant,
+) -> Result<(Result<Message, DecodeError>, Vec<u8>), Error> {
+    let remaining = expires.saturating_duration_since(Instant::now());
+    socket.set_read_timeout(Some(remaining.max(Duration::from_millis(1))))?;
+
+    let received = socket.recv(buf)?;
+    if received == buf.len() {
+        return Ok((Err(DecodeError::Truncated { capacity: buf.len() }), buf.to_vec()));
+    }
+
+    Ok((Message::decode(&buf[..received]), buf[..received].to_vec()))
+}
+
+fn exchange(request: &InFlight, reply: Message, received: Vec<u8>, server: SocketAddr) -> Exchange {
+    Exchange {
+        reply,
+        sent: request.datagram.clone(),
+        received,
+        server,
+        attempts: request.schedule.attempts(),
+        rtt: request.sent.elapsed(),
+        discarded: 0,
+    }
+}
+
+fn expired(request: &InFlight, last_error: &Error) -> AuthError {
+    match last_error.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => AuthError::Timeout {
+            attempts: request.schedule.attempts(),
+        },
+        kind => AuthError::Io(Error::new(kind, last_error.to_string())),
+    }
+}
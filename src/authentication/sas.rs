@@ -1,111 +1,106 @@
-use std::{io::Error, net::UdpSocket};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
 
-use super::package::sas::{SASPackageRequest, SASPackageResponse, SASPackageStatus, SASPackageValidation};
+use crate::error::ParseTokenError;
+
+/// Size in bytes of the ID field.
+pub const ID_LEN: usize = 12;
+/// Size in bytes of a SAS or GAS token.
+pub const TOKEN_LEN: usize = 64;
 
-const MIN_REQUEST_ARGS: usize = 2;
-const MIN_VALIDATION_ARGS: usize = 1;
-const REQUEST_BUFFER_SIZE: usize = 82;
-const STATUS_BUFFER_SIZE: usize = 100;
 const EXPECTED_SAS_PARTS: usize = 3;
-const ARGUMENT_ERROR: &str = "Insufficient arguments provided!";
-const ERROR_MSG_SEND_PACKAGE: &str = "Failed to send package!";
-const ERROR_MSG_RECV_PACKAGE: &str = "Failed to receive package!";
-const MAX_RESPONSE_ATTEMPTS: usize = 3;
-
-fn attempt_request<F, G>(socket: &UdpSocket, args: &[String], req_fn: F, res_fn: G)
-where
-    F: Fn(&UdpSocket, &[String]),
-    G: Fn(&UdpSocket) -> Result<usize, Error>,
-{
-    let mut request_result: Result<usize, Error> = Ok(0);
-
-    for _ in 0..MAX_RESPONSE_ATTEMPTS {
-        req_fn(socket, args);
-        request_result = res_fn(socket);
-
-        if request_result.is_ok() {
-            break;
-        }
-    }
 
-    if let Err(e) = request_result {
-        eprintln!("{ERROR_MSG_RECV_PACKAGE} {:?}", e.to_string());
-        std::process::exit(1);
-    }
+/// Single Authentication Sequence, written as `id:nonce:token`.
+///
+/// The ID is stored NUL-padded exactly as it travels on the wire.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sas {
+    pub id: [u8; ID_LEN],
+    pub nonce: u32,
+    pub token: [u8; TOKEN_LEN],
 }
 
+impl Sas {
+    pub fn new(id: &str, nonce: u32, token: &str) -> Result<Self, ParseTokenError> {
+        Ok(Self {
+            id: parse_id(id)?,
+            nonce,
+            token: parse_token(token)?,
+        })
+    }
 
-pub fn itr(socket: &UdpSocket, args: &[String]) {
-    attempt_request(socket, args, request, response);
-}
+    /// The ID without its NUL padding.
+    pub fn id_str(&self) -> Cow<'_, str> {
+        let len = self.id.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+        String::from_utf8_lossy(&self.id[..len])
+    }
 
-pub fn itv(socket: &UdpSocket, args: &[String]) {
-    attempt_request(socket, args, validation, status);
+    pub fn token_str(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.token)
+    }
 }
 
-fn request(socket: &UdpSocket, args: &[String]) {
-    if args.len() < MIN_REQUEST_ARGS {
-        eprintln!("{}", ARGUMENT_ERROR);
-        std::process::exit(1);
+impl fmt::Display for Sas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.id_str(), self.nonce, self.token_str())
     }
+}
 
-    let id = args.first().unwrap();
-    let nonce = args.get(1).unwrap();
-    let pack = SASPackageRequest::new(id, nonce);
+impl FromStr for Sas {
+    type Err = ParseTokenError;
 
-    if let Err(e) = socket.send(pack.as_bytes()) {
-        eprintln!("{ERROR_MSG_SEND_PACKAGE} {:?}", e.to_string());
-        std::process::exit(1);
-    }
-}
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
 
-fn response(socket: &UdpSocket) -> Result<usize, Error> {
-    let mut buf = vec![0; REQUEST_BUFFER_SIZE];
-    let buf = match socket.recv(&mut buf) {
-        Ok(received) => &buf[..received],
-        Err(e) => return Err(e)
-    };
+        if parts.len() != EXPECTED_SAS_PARTS {
+            return Err(ParseTokenError::FieldCount {
+                expected: EXPECTED_SAS_PARTS,
+                found: parts.len(),
+            });
+        }
 
-    let pack = SASPackageResponse::new(buf);
-    pack.print_sas();
+        let nonce = parts[1]
+            .parse::<u32>()
+            .map_err(|_| ParseTokenError::InvalidNonce(parts[1].to_string()))?;
 
-    Ok(buf.len())
+        Self::new(parts[0], nonce, parts[2])
+    }
 }
 
-fn validation(socket: &UdpSocket, args: &[String]) {
-    if args.len() < MIN_VALIDATION_ARGS {
-        eprintln!("{}", ARGUMENT_ERROR);
-        std::process::exit(1);
+/// Validates an ID and pads it with NULs to its wire size.
+///
+/// `:` and `+` are rejected, since the text form of a SAS or GAS could not
+/// be parsed back.
+pub fn parse_id(id: &str) -> Result<[u8; ID_LEN], ParseTokenError> {
+    if !id.is_ascii() {
+        return Err(ParseTokenError::NonAsciiId);
     }
 
-    let sas: Vec<&str> = args.first().unwrap().split(':').collect();
-
-    if sas.len() < EXPECTED_SAS_PARTS {
-        eprintln!("{}", ARGUMENT_ERROR);
-        std::process::exit(1);
+    if let Some(separator) = id.chars().find(|c| matches!(c, ':' | '+')) {
+        return Err(ParseTokenError::IdSeparator(separator));
     }
 
-    let id = sas[0];
-    let nonce = sas[1];
-    let token = sas[2];
-
-    let pack = SASPackageValidation::new(id, nonce, token);
-
-    if let Err(e) = socket.send(pack.as_bytes()) {
-        eprintln!("{ERROR_MSG_SEND_PACKAGE} {:?}", e.to_string());
-        std::process::exit(1);
+    if id.len() > ID_LEN {
+        return Err(ParseTokenError::IdTooLong {
+            max: ID_LEN,
+            found: id.len(),
+        });
     }
-}
 
-fn status(socket: &UdpSocket) -> Result<usize, Error> {
-    let mut buf = vec![0; STATUS_BUFFER_SIZE];
-    let buf = match socket.recv(&mut buf) {
-        Ok(received) => &buf[..received],
-        Err(e) => return Err(e)
-    };
+    let mut id_bytes = [0u8; ID_LEN];
+    id_bytes[..id.len()].copy_from_slice(id.as_bytes());
+    Ok(id_bytes)
+}
 
-    let pack = SASPackageStatus::new(buf);
-    pack.print_status();
+/// Validates that a token is exactly 64 hex digits.
+pub fn parse_token(token: &str) -> Result<[u8; TOKEN_LEN], ParseTokenError> {
+    if let Some(c) = token.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(ParseTokenError::TokenNotHex(c));
+    }
 
-    Ok(buf.len())
+    token.as_bytes().try_into().map_err(|_| ParseTokenError::TokenLength {
+        expected: TOKEN_LEN,
+        found: token.len(),
+    })
 }
@@ -0,0 +1,147 @@
+use std::io::{Error, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use super::package::{DecodeError, Message, MAX_DATAGRAM_SIZE};
+use crate::error::AuthError;
+use crate::retry::{self, RetryPolicy};
+
+/// A request answered by the server.
+#[derive(Debug, Clone)]
+pub struct Exchange {
+    pub reply: Message,
+    /// The request datagram, as sent on every attempt.
+    pub sent: Vec<u8>,
+    /// The reply datagram, as received.
+    pub received: Vec<u8>,
+    /// The server address that answered.
+    pub server: SocketAddr,
+    /// Times the request was sent, including the one that got answered.
+    pub attempts: usize,
+    /// Time between the last transmission and the reply.
+    pub rtt: Duration,
+    /// Datagrams dropped because they did not answer the request.
+    pub discarded: usize,
+}
+
+/// A request that got no usable reply, as it went over the wire.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    /// The request datagram, as sent on every attempt.
+    pub sent: Vec<u8>,
+    /// A datagram that arrived but could not be decoded.
+    pub received: Option<Vec<u8>>,
+    /// The server address last tried.
+    pub server: SocketAddr,
+}
+
+/// Sends `request` and waits for its reply, retransmitting per `policy`.
+///
+/// The request is encoded once and the same bytes go out on every attempt.
+/// Datagrams that do not answer it are discarded, so any message type with a
+/// reply gets retransmission and correlation without extra code. A request
+/// that fails once it was sent is described in `failure`.
+pub(crate) fn transact(
+    socket: &UdpSocket,
+    request: &Message,
+    policy: &RetryPolicy,
+    failure: &mut Option<Failure>,
+) -> Result<Exchange, AuthError> {
+    let server = canonical(socket.peer_addr()?);
+    let datagram = request.encode();
+    let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+
+    let mut schedule = policy.start();
+    let mut discarded = 0;
+    let mut malformed = None;
+    let mut last_error = Error::from(ErrorKind::TimedOut);
+
+    let error = 'failed: {
+        while let Some(timeout) = schedule.next_timeout() {
+            if let Err(e) = socket.send(&datagram) {
+                break 'failed e.into();
+            }
+            let sent = Instant::now();
+
+            match recv_reply(socket, &mut buf, request, timeout, &mut discarded, &mut malformed) {
+                Ok((reply, received)) => {
+                    return Ok(Exchange {
+                        reply,
+                        sent: datagram,
+                        received,
+                        server,
+                        attempts: schedule.attempts(),
+                        rtt: sent.elapsed(),
+                        discarded,
+                    })
+                }
+                Err(AuthError::Io(e)) => last_error = e,
+                Err(e) => break 'failed e,
+            }
+        }
+
+        match last_error.kind() {
+            ErrorKind::WouldBlock | ErrorKind::TimedOut => AuthError::Timeout { attempts: schedule.attempts() },
+            _ => AuthError::Io(last_error),
+        }
+    };
+
+    *failure = Some(Failure {
+        sent: datagram,
+        received: malformed,
+        server,
+    });
+    Err(error)
+}
+
+/// Receives datagrams until one answers `request` or `timeout` elapses, and
+/// returns it decoded and as received.
+///
+/// Well-formed datagrams that are not a reply to `request` are dropped and
+/// counted in `discarded`. The timeout bounds the whole wait, not each
+/// `recv`, so a stream of foreign datagrams cannot stall the caller.
+///
+/// `buf` must be able to hold the largest possible datagram: one that fills
+/// it completely may have been cut short by `recv` and is rejected rather
+/// than decoded.
+fn recv_reply(
+    socket: &UdpSocket,
+    buf: &mut [u8],
+    request: &Message,
+    timeout: Duration,
+    discarded: &mut usize,
+    malformed: &mut Option<Vec<u8>>,
+) -> Result<(Message, Vec<u8>), AuthError> {
+    let deadline = retry::later(Instant::now(), timeout);
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(AuthError::Io(Error::from(ErrorKind::TimedOut)));
+        }
+        socket.set_read_timeout(Some(remaining))?;
+
+        let received = socket.recv(buf)?;
+        let datagram = &buf[..received];
+        let decoded = if received == buf.len() {
+            Err(DecodeError::Truncated { capacity: buf.len() })
+        } else {
+            Message::decode(datagram)
+        };
+
+        match decoded {
+            Ok(reply) if reply.is_reply_to(request) => return Ok((reply, datagram.to_vec())),
+            Ok(_) => *discarded += 1,
+            Err(e) => {
+                *malformed = Some(datagram.to_vec());
+                return Err(e.into());
+            }
+        }
+    }
+}
+
+/// `address` with an IPv4-mapped IPv6 address turned back into IPv4, as a
+/// dual-stack socket reports its IPv4 peers.
+pub(crate) fn canonical(address: SocketAddr) -> SocketAddr {
+    SocketAddr::new(address.ip().to_canonical(), address.port())
+}
@@ -0,0 +1,79 @@
+use std::env;
+use std::fs;
+
+use udp_auth_client::server::{Server, TokenIssuer};
+
+const DEFAULT_BIND_ADDRESS: &str = "::";
+const USAGE: &str = "Usage: udp-auth-server [--secret <secret> | --secret-file <path>] <port> [bind-address]";
+
+struct Options {
+    port: u16,
+    address: String,
+    secret: Option<Vec<u8>>,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let options = match parse_options(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("{USAGE}");
+            std::process::exit(1);
+        }
+    };
+
+    let tokens = match options.secret {
+        Some(secret) => TokenIssuer::new(secret),
+        None => TokenIssuer::random(),
+    };
+
+    let server = match Server::bind((options.address.as_str(), options.port), tokens) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Failed to bind socket to address: {:?}", e.to_string());
+            std::process::exit(1);
+        }
+    };
+
+    match server.local_addr() {
+        Ok(local) => println!("Listening on {local}"),
+        Err(e) => eprintln!("Failed to read local address: {:?}", e.to_string()),
+    }
+
+    if let Err(e) = server.run() {
+        eprintln!("Server stopped: {:?}", e.to_string());
+        std::process::exit(1);
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut secret = None;
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--secret" => {
+                let value = args.next().ok_or("Missing value for --secret!")?;
+                secret = Some(value.as_bytes().to_vec());
+            }
+            "--secret-file" => {
+                let path = args.next().ok_or("Missing value for --secret-file!")?;
+                let contents = fs::read(path).map_err(|e| format!("Failed to read secret file {path}: {e}"))?;
+                secret = Some(contents.trim_ascii_end().to_vec());
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    let port = positional.first().ok_or("Insufficient arguments provided!")?;
+    let port = port
+        .parse::<u16>()
+        .map_err(|e| format!("Invalid port number: {:?}", e.to_string()))?;
+
+    let address = positional.get(1).map_or(DEFAULT_BIND_ADDRESS, |a| a.as_str()).to_string();
+
+    Ok(Options { port, address, secret })
+}
@@ -0,0 +1,285 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use clap::error::ErrorKind;
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use udp_auth_client::authentication::sas::{self, ID_LEN};
+use udp_auth_client::{AuthError, Gas, Message, ParseTokenError, Sas, TokenType};
+
+use crate::output::Format;
+use crate::Request;
+
+pub const DEFAULT_WINDOW: usize = 16;
+/// The longest timeout or deadline accepted, a day.
+pub const MAX_SECONDS: f64 = 86_400.0;
+
+/// Client for the UDP authentication token protocol.
+#[derive(Debug, Parser)]
+#[command(name = "udp-auth-client", version, about, subcommand_value_name = "COMMAND")]
+pub struct Cli {
+    /// Server host name or address, unless given with --host.
+    pub host: Option<String>,
+    /// Server UDP port, unless given with --port.
+    pub port: Option<u16>,
+    #[command(flatten)]
+    pub options: GlobalOptions,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Options accepted anywhere on the command line. Each can also be set
+/// from the environment, and otherwise comes from the selected profile.
+#[derive(Debug, Args)]
+pub struct GlobalOptions {
+    /// Server host name or address.
+    #[arg(long = "host", value_name = "HOST", global = true, env = "UDP_AUTH_HOST")]
+    pub server_host: Option<String>,
+    /// Server UDP port.
+    #[arg(long = "port", value_name = "PORT", global = true, env = "UDP_AUTH_PORT")]
+    pub server_port: Option<u16>,
+    /// A server to fail over to, as <host>:<port>. May be repeated.
+    #[arg(
+        long = "server",
+        value_name = "HOST:PORT",
+        global = true,
+        env = "UDP_AUTH_SERVERS",
+        value_delimiter = ',',
+        value_parser = parse_server
+    )]
+    pub servers: Vec<(String, u16)>,
+    /// Use IPv4 only.
+    #[arg(short = '4', long = "ipv4", global = true, conflicts_with = "ipv6")]
+    pub ipv4: bool,
+    /// Use IPv6 only.
+    #[arg(short = '6', long = "ipv6", global = true)]
+    pub ipv6: bool,
+    /// Local address and port to send from.
+    #[arg(long, value_name = "ADDR:PORT", global = true, env = "UDP_AUTH_BIND")]
+    pub bind: Option<SocketAddr>,
+    /// Network interface to send through (Linux only).
+    #[arg(long, value_name = "NAME", global = true, env = "UDP_AUTH_INTERFACE")]
+    pub interface: Option<String>,
+    /// Profile of the config file to use.
+    #[arg(long, value_name = "NAME", global = true, env = "UDP_AUTH_PROFILE")]
+    pub profile: Option<String>,
+    /// Config file, instead of ~/.config/udp-auth-client/config.toml.
+    #[arg(long, value_name = "FILE", global = true, env = "UDP_AUTH_CONFIG")]
+    pub config: Option<String>,
+    /// Time to wait for the first reply, doubled on each retransmission [default: 5].
+    #[arg(long, value_name = "SECONDS", global = true, env = "UDP_AUTH_TIMEOUT", value_parser = parse_seconds)]
+    pub timeout: Option<Duration>,
+    /// Retransmissions after the first attempt [default: 2].
+    #[arg(long, value_name = "N", global = true, env = "UDP_AUTH_RETRIES")]
+    pub retries: Option<usize>,
+    /// Give up once this much time has passed overall.
+    #[arg(long, value_name = "SECONDS", global = true, env = "UDP_AUTH_DEADLINE", value_parser = parse_seconds)]
+    pub deadline: Option<Duration>,
+    /// Commands kept in flight at once by batch, group-enroll and roster [default: 16].
+    #[arg(long, value_name = "N", global = true, env = "UDP_AUTH_WINDOW", value_parser = parse_window)]
+    pub window: Option<usize>,
+    /// Answer itr from the token store when it holds a matching SAS.
+    #[arg(long, global = true)]
+    pub use_cache: bool,
+    /// Output format [default: text].
+    #[arg(long, value_name = "FORMAT", global = true, env = "UDP_AUTH_OUTPUT", value_enum)]
+    pub output: Option<Format>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    #[command(flatten)]
+    Protocol(ProtocolCommand),
+    /// Request the SAS of every member, then the GAS covering them.
+    #[command(group(ArgGroup::new("members").args(["member", "members_file"]).required(true).multiple(true)))]
+    GroupEnroll {
+        /// A member, given as <id>:<nonce>.
+        #[arg(long, value_name = "ID:NONCE", value_parser = parse_member)]
+        member: Vec<(String, u32)>,
+        /// A file with one <id>:<nonce> member per line, or - for stdin.
+        #[arg(long, value_name = "FILE")]
+        members_file: Vec<String>,
+    },
+    /// Obtain every SAS and GAS of a TOML or CSV roster.
+    Roster {
+        /// The roster file.
+        file: String,
+        /// Write the results to this file instead of stdout.
+        #[arg(long, value_name = "FILE")]
+        out: Option<String>,
+    },
+    /// Run one command per line of a file, or of stdin for -.
+    Batch {
+        /// The batch file.
+        file: String,
+    },
+    /// Open a shell on the server.
+    Interactive,
+    /// Inspect the token store. Needs no server.
+    Tokens {
+        #[command(subcommand)]
+        command: TokensCommand,
+    },
+}
+
+/// The commands that send one protocol message.
+#[derive(Debug, Clone, Subcommand)]
+pub enum ProtocolCommand {
+    /// Request an individual token.
+    Itr {
+        #[arg(value_parser = parse_id)]
+        id: [u8; ID_LEN],
+        #[arg(value_parser = parse_nonce)]
+        nonce: u32,
+    },
+    /// Validate an individual token.
+    Itv { sas: Sas },
+    /// Request a group token.
+    Gtr {
+        /// Number of SAS values that follow.
+        n: usize,
+        #[arg(value_name = "SAS")]
+        members: Vec<Sas>,
+    },
+    /// Validate a group token.
+    Gtv { gas: Gas },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TokensCommand {
+    /// Every stored token.
+    List,
+    /// The SAS of an ID and every GAS it is a member of.
+    Show { id: String },
+    /// The whole store as JSON.
+    Export,
+}
+
+/// One line of a batch or of the interactive shell.
+#[derive(Debug, Parser)]
+#[command(no_binary_name = true, disable_help_subcommand = true)]
+struct Line {
+    #[command(subcommand)]
+    command: ProtocolCommand,
+}
+
+impl Command {
+    /// The command as typed, for error reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Protocol(command) => command.name(),
+            Self::GroupEnroll { .. } => "group-enroll",
+            Self::Roster { .. } => "roster",
+            Self::Batch { .. } => "batch",
+            Self::Interactive => "interactive",
+            Self::Tokens { .. } => "tokens",
+        }
+    }
+}
+
+impl ProtocolCommand {
+    /// Parses the words of a batch or shell line.
+    pub fn parse_line(words: &[String]) -> Result<Self, AuthError> {
+        Line::try_parse_from(words)
+            .map(|line| line.command)
+            .map_err(|e| AuthError::InvalidInput(message(&e)))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Itr { .. } => "itr",
+            Self::Itv { .. } => "itv",
+            Self::Gtr { .. } => "gtr",
+            Self::Gtv { .. } => "gtv",
+        }
+    }
+
+    /// The message to send and the reply type it expects.
+    pub fn into_request(self) -> Result<Request, AuthError> {
+        let command = self.name().to_string();
+
+        let (message, expected) = match self {
+            Self::Itr { id, nonce } => (Message::IndividualTokenRequest { id, nonce }, TokenType::IndividualTokenResponse),
+            Self::Itv { sas } => (Message::IndividualTokenValidation(sas), TokenType::IndividualTokenStatus),
+            Self::Gtr { n, members } => {
+                if members.len() != n {
+                    return Err(AuthError::InvalidInput(format!(
+                        "Expected {} SAS values, but received {}",
+                        n,
+                        members.len()
+                    )));
+                }
+                (Message::GroupTokenRequest(members), TokenType::GroupTokenResponse)
+            }
+            Self::Gtv { gas } => (Message::GroupTokenValidation(gas), TokenType::GroupTokenStatus),
+        };
+
+        Ok(Request {
+            command,
+            message,
+            expected,
+        })
+    }
+}
+
+/// The first line of a clap error, without its `error: ` prefix.
+pub fn message(error: &clap::Error) -> String {
+    if error.kind() == ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand {
+        return "Missing command! See --help for the commands.".to_string();
+    }
+
+    let rendered = error.render().to_string();
+    let first = rendered.lines().next().unwrap_or_default();
+
+    first.strip_prefix("error: ").unwrap_or(first).to_string()
+}
+
+/// Parses a group member given as `<id>:<nonce>`.
+pub fn parse_member(member: &str) -> Result<(String, u32), AuthError> {
+    let (id, nonce) = member
+        .rsplit_once(':')
+        .ok_or_else(|| AuthError::InvalidInput(format!("Invalid member {member:?}! Expected <id>:<nonce>.")))?;
+
+    Ok((id.to_string(), parse_nonce(nonce)?))
+}
+
+/// Parses a server given as `<host>:<port>`, or `[<address>]:<port>` for
+/// an IPv6 address.
+pub fn parse_server(server: &str) -> Result<(String, u16), String> {
+    let (host, port) = server
+        .rsplit_once(':')
+        .filter(|(host, _)| !host.is_empty())
+        .ok_or_else(|| format!("{server:?} is not <host>:<port>"))?;
+    let port = port.parse::<u16>().map_err(|_| format!("{port:?} is not a port number"))?;
+    let host = host.strip_prefix('[').and_then(|host| host.strip_suffix(']')).unwrap_or(host);
+
+    Ok((host.to_string(), port))
+}
+
+fn parse_id(id: &str) -> Result<[u8; ID_LEN], AuthError> {
+    Ok(sas::parse_id(id)?)
+}
+
+fn parse_nonce(nonce: &str) -> Result<u32, ParseTokenError> {
+    nonce.parse::<u32>().map_err(|_| ParseTokenError::InvalidNonce(nonce.to_string()))
+}
+
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(seconds)
+        .ok_or_else(|| format!("not a number of seconds up to {MAX_SECONDS}"))
+}
+
+/// `seconds` as a duration, unless it is negative or above [`MAX_SECONDS`].
+pub fn seconds(seconds: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(seconds).ok().filter(|_| seconds <= MAX_SECONDS)
+}
+
+fn parse_window(value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|window| *window > 0)
+        .ok_or_else(|| "not a positive number".to_string())
+}
@@ -0,0 +1,284 @@
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::cell::{Cell, RefCell};
+use std::time::Duration;
+
+use crate::authentication::check;
+use crate::authentication::package::Message;
+use crate::authentication::pipeline;
+use crate::authentication::sas;
+use crate::authentication::transaction::{self, Exchange, Failure};
+use crate::error::AuthError;
+use crate::failover::{self, unanswered, Failover};
+use crate::retry::RetryPolicy;
+use crate::socket::SocketOptions;
+use crate::{Gas, Sas};
+
+const WRITE_TIMEOUT_SECONDS: u64 = 5;
+
+/// Client for the UDP authentication protocol.
+///
+/// Wraps a connected `UdpSocket` and exposes one method per protocol
+/// operation. Retransmission on timeout follows the client's
+/// [`RetryPolicy`].
+///
+/// A client may know several server addresses. When the one it is connected
+/// to does not answer, it fails over to the next, and keeps using whichever
+/// answered.
+///
+/// A `Client` handles one request at a time. It is `Send` but not `Sync`;
+/// `AsyncClient` (feature `async`) takes concurrent calls.
+pub struct Client {
+    socket: UdpSocket,
+    /// Every known server address, in the order they are tried.
+    servers: Vec<SocketAddr>,
+    /// Index in `servers` of the address the socket is connected to.
+    current: Cell<usize>,
+    policy: RetryPolicy,
+    discarded: Cell<usize>,
+    last: RefCell<Option<Exchange>>,
+    failure: RefCell<Option<Failure>>,
+}
+
+impl Client {
+    /// Binds a local socket and connects it to the authentication server,
+    /// failing over between all of its addresses.
+    pub fn connect<A: ToSocketAddrs>(server: A) -> Result<Self, AuthError> {
+        Self::connect_all([server])
+    }
+
+    /// Binds a local socket and connects it to the first address of the
+    /// first server, failing over to the others in order.
+    pub fn connect_all<A: ToSocketAddrs>(servers: impl IntoIterator<Item = A>) -> Result<Self, AuthError> {
+        Self::connect_with(servers, &SocketOptions::default())
+    }
+
+    /// Like [`Client::connect_all`], with the local socket set up per
+    /// `options`. Server addresses the socket cannot reach are left out.
+    ///
+    /// The addresses of each server alternate between IPv6 and IPv4, so that
+    /// a family that is broken on this host costs at most one failover.
+    /// Servers that cannot be resolved are skipped as long as one can.
+    pub fn connect_with<A: ToSocketAddrs>(
+        servers: impl IntoIterator<Item = A>,
+        options: &SocketOptions,
+    ) -> Result<Self, AuthError> {
+        let resolved = servers.into_iter().map(|server| Ok(server.to_socket_addrs()?.collect()));
+        let mut addresses = failover::addresses(resolved)?;
+
+        let socket = options.bind(&mut addresses)?;
+        let timeout_duration = Duration::new(WRITE_TIMEOUT_SECONDS, 0);
+
+        socket.set_write_timeout(Some(timeout_duration))?;
+        socket.connect(addresses[0])?;
+
+        Ok(Self {
+            servers: addresses,
+            ..Self::new(socket)
+        })
+    }
+
+    /// Wraps an already connected socket.
+    pub fn new(socket: UdpSocket) -> Self {
+        Self {
+            servers: socket.peer_addr().into_iter().collect(),
+            current: Cell::new(0),
+            socket,
+            policy: RetryPolicy::default(),
+            discarded: Cell::new(0),
+            last: RefCell::new(None),
+            failure: RefCell::new(None),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    pub fn socket(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    /// Every server address the client may fail over to, in order.
+    pub fn servers(&self) -> &[SocketAddr] {
+        &self.servers
+    }
+
+    /// Number of datagrams dropped so far because they did not answer the
+    /// request in flight, such as late replies to an earlier retransmission.
+    pub fn discarded_datagrams(&self) -> usize {
+        self.discarded.get()
+    }
+
+    /// The most recent answered exchange, with its attempt count and RTT.
+    pub fn last_exchange(&self) -> Option<Exchange> {
+        self.last.borrow().clone()
+    }
+
+    /// The datagrams of the most recent request sent with
+    /// [`Client::transact`] that got no usable reply.
+    pub fn last_failure(&self) -> Option<Failure> {
+        self.failure.borrow().clone()
+    }
+
+    /// Sends any request and returns the reply that answers it. Group
+    /// messages with more members than fit in a datagram are rejected.
+    pub fn transact(&self, request: &Message) -> Result<Exchange, AuthError> {
+        check::member_count(request)?;
+
+        let mut failure = None;
+        let result = self.fail_over(|socket, policy| transaction::transact(socket, request, policy, &mut failure));
+
+        let exchange = match result {
+            Ok(exchange) => exchange,
+            Err(e) => {
+                self.failure.replace(failure);
+                return Err(e);
+            }
+        };
+
+        self.discarded.set(self.discarded.get() + exchange.discarded);
+        self.last.replace(Some(exchange.clone()));
+
+        Ok(exchange)
+    }
+
+    /// Sends all of `requests`, keeping up to `window` of them in flight at
+    /// once, and returns their results in the same order.
+    ///
+    /// Each request is retransmitted on its own under the client's retry
+    /// policy, and anonymous replies are handled as `pipeline::pipeline`
+    /// describes. Requests that got no answer are sent again to the next
+    /// server, and group messages too large for a datagram are never sent.
+    pub fn transact_all(&self, requests: &[Message], window: usize) -> Vec<Result<Exchange, AuthError>> {
+        let mut discarded = 0;
+        let mut results: Vec<Option<Result<Exchange, AuthError>>> =
+            requests.iter().map(|request| check::member_count(request).err().map(Err)).collect();
+        let mut pending: Vec<usize> = (0..requests.len()).filter(|index| results[*index].is_none()).collect();
+
+        let _ = self.fail_over(|socket, policy| {
+            let messages: Vec<Message> = pending.iter().map(|index| requests[*index].clone()).collect();
+            let outcomes = pipeline::pipeline(socket, &messages, window, policy, &mut discarded);
+            let mut unanswered_requests = Vec::new();
+
+            for (index, outcome) in pending.iter().zip(outcomes) {
+                if unanswered(&outcome) {
+                    unanswered_requests.push(*index);
+                }
+                results[*index] = Some(outcome);
+            }
+
+            pending = unanswered_requests;
+            if pending.is_empty() {
+                Ok(())
+            } else {
+                Err(AuthError::Timeout { attempts: 0 })
+            }
+        });
+        self.discarded.set(self.discarded.get() + discarded);
+
+        results
+            .into_iter()
+            .map(|result| result.unwrap_or(Err(AuthError::Timeout { attempts: 0 })))
+            .collect()
+    }
+
+    /// Runs `operation` on the current server and then, for as long as it
+    /// gets no answer, once on each of the other servers in turn.
+    fn fail_over<T>(
+        &self,
+        mut operation: impl FnMut(&UdpSocket, &RetryPolicy) -> Result<T, AuthError>,
+    ) -> Result<T, AuthError> {
+        let mut failover = Failover::start(&self.policy, self.servers.len());
+        let first = self.current.get();
+        let mut result = Err(AuthError::Timeout { attempts: 0 });
+
+        while let Some((offset, policy)) = failover.next_server() {
+            if offset > 0 {
+                let index = (first + offset) % self.servers.len();
+                self.socket.connect(self.servers[index])?;
+                self.current.set(index);
+            }
+
+            result = operation(&self.socket, policy);
+
+            if !failover.unanswered(&result) {
+                return result;
+            }
+        }
+
+        failover.finish(result)
+    }
+
+    /// Requests an individual token (SAS) for `id` and `nonce`.
+    pub fn request_individual_token(&self, id: &str, nonce: u32) -> Result<Sas, AuthError> {
+        let request = Message::IndividualTokenRequest {
+            id: sas::parse_id(id)?,
+            nonce,
+        };
+
+        check::individual_token(self.transact(&request)?.reply)
+    }
+
+    /// Requests an individual token for every `(id, nonce)` pair, pipelining
+    /// up to `window` requests. Results are in the order of `requests`.
+    pub fn request_individual_tokens(&self, requests: &[(&str, u32)], window: usize) -> Vec<Result<Sas, AuthError>> {
+        let mut messages = Vec::with_capacity(requests.len());
+        let mut results: Vec<Option<Result<Sas, AuthError>>> = Vec::with_capacity(requests.len());
+
+        for (id, nonce) in requests {
+            match sas::parse_id(id) {
+                Ok(id) => {
+                    messages.push(Message::IndividualTokenRequest { id, nonce: *nonce });
+                    results.push(None);
+                }
+                Err(e) => results.push(Some(Err(e.into()))),
+            }
+        }
+
+        let mut replies = self.transact_all(&messages, window).into_iter();
+
+        results
+            .into_iter()
+            .map(|result| {
+                result.unwrap_or_else(|| check::individual_token(replies.next().expect("one reply per request")?.reply))
+            })
+            .collect()
+    }
+
+    /// Asks the server whether `sas` is valid.
+    pub fn validate_individual_token(&self, sas: &Sas) -> Result<bool, AuthError> {
+        let request = Message::IndividualTokenValidation(sas.clone());
+
+        check::individual_status(self.transact(&request)?.reply)
+    }
+
+    /// Requests a group token (GAS) covering every SAS in `members`.
+    pub fn request_group_token(&self, members: &[Sas]) -> Result<Gas, AuthError> {
+        let request = Message::GroupTokenRequest(members.to_vec());
+
+        check::group_token(self.transact(&request)?.reply)
+    }
+
+    /// Requests a group token for every member list in `groups`, pipelining
+    /// up to `window` requests. Results are in the order of `groups`.
+    pub fn request_group_tokens(&self, groups: &[Vec<Sas>], window: usize) -> Vec<Result<Gas, AuthError>> {
+        let requests: Vec<Message> = groups.iter().cloned().map(Message::GroupTokenRequest).collect();
+
+        self.transact_all(&requests, window)
+            .into_iter()
+            .map(|exchange| check::group_token(exchange?.reply))
+            .collect()
+    }
+
+    /// Asks the server whether `gas` is valid.
+    pub fn validate_group_token(&self, gas: &Gas) -> Result<bool, AuthError> {
+        let request = Message::GroupTokenValidation(gas.clone());
+
+        check::group_status(self.transact(&request)?.reply)
+    }
+}
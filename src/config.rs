@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+use udp_auth_client::AuthError;
+
+use crate::cli;
+use crate::output::Format;
+
+const CONFIG_DIRECTORY: &str = "udp-auth-client";
+const CONFIG_FILE: &str = "config.toml";
+
+/// Named server profiles, read from `$XDG_CONFIG_HOME/udp-auth-client/config.toml`.
+///
+/// ```toml
+/// default = "course"
+///
+/// [profile.course]
+/// host = "vcm-23691.vm.duke.edu"
+/// port = 51001
+///
+/// [profile.staging]
+/// servers = ["staging-1.example.edu:51001", "staging-2.example.edu:51001"]
+/// timeout = 2.5
+/// retries = 4
+/// output = "json"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(skip)]
+    path: PathBuf,
+    /// The profile used when none is selected.
+    pub default: Option<String>,
+    #[serde(rename = "profile", default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Settings of one deployment. Anything left out keeps its built-in default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    /// Servers to fail over to, as `<host>:<port>`.
+    #[serde(default)]
+    pub servers: Vec<String>,
+    /// Seconds to wait for the first reply.
+    pub timeout: Option<f64>,
+    pub retries: Option<usize>,
+    /// Seconds before giving up overall.
+    pub deadline: Option<f64>,
+    pub window: Option<usize>,
+    pub output: Option<Format>,
+    /// Local address and port to send from.
+    pub bind: Option<SocketAddr>,
+    /// Network interface to send through.
+    pub interface: Option<String>,
+}
+
+impl Config {
+    /// Reads `path`, or the default location when it is `None`. A missing
+    /// default file is an empty configuration.
+    pub fn load(path: Option<&str>) -> Result<Self, AuthError> {
+        let (path, required) = match path.map(PathBuf::from).or_else(default_path) {
+            Some(found) => (found, path.is_some()),
+            None => return Ok(Self::default()),
+        };
+
+        let mut config: Self = match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text)
+                .map_err(|e| AuthError::InvalidInput(format!("Invalid config file {}: {e}", path.display())))?,
+            Err(e) if e.kind() == ErrorKind::NotFound && !required => Self::default(),
+            Err(e) => return Err(AuthError::InvalidInput(format!("Cannot read {}: {e}", path.display()))),
+        };
+
+        config.path = path;
+        Ok(config)
+    }
+
+    /// The profile named `name`, or the default profile when it is `None`.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile, AuthError> {
+        let Some(name) = name.or(self.default.as_deref()) else {
+            return Ok(Profile::default());
+        };
+
+        self.profiles.get(name).cloned().ok_or_else(|| {
+            let known = self.profiles.keys().map(String::as_str).collect::<Vec<_>>().join(", ");
+
+            AuthError::InvalidInput(format!(
+                "Unknown profile {name:?}! Profiles in {}: {}",
+                self.path.display(),
+                if known.is_empty() { "none" } else { &known }
+            ))
+        })
+    }
+}
+
+impl Profile {
+    pub fn servers(&self) -> Result<Vec<(String, u16)>, AuthError> {
+        self.servers
+            .iter()
+            .map(|server| cli::parse_server(server))
+            .collect::<Result<_, _>>()
+            .map_err(|e| AuthError::InvalidInput(format!("Invalid server in profile: {e}.")))
+    }
+
+    pub fn timeout(&self) -> Result<Option<Duration>, AuthError> {
+        self.timeout.map(|seconds| seconds_setting("timeout", seconds)).transpose()
+    }
+
+    pub fn deadline(&self) -> Result<Option<Duration>, AuthError> {
+        self.deadline.map(|seconds| seconds_setting("deadline", seconds)).transpose()
+    }
+}
+
+fn seconds_setting(name: &str, seconds: f64) -> Result<Duration, AuthError> {
+    cli::seconds(seconds).ok_or_else(|| {
+        AuthError::InvalidInput(format!(
+            "Invalid {name} in profile: {seconds} is not a number of seconds up to {}.",
+            cli::MAX_SECONDS
+        ))
+    })
+}
+
+/// `$XDG_CONFIG_HOME/udp-auth-client/config.toml`, or under `~/.config`.
+fn default_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_home.join(CONFIG_DIRECTORY).join(CONFIG_FILE))
+}
@@ -0,0 +1,67 @@
+use std::io;
+
+use thiserror::Error;
+
+use crate::authentication::check::{ErrorCode, TokenType};
+use crate::authentication::package::DecodeError;
+
+/// Every way a protocol operation can fail.
+#[derive(Debug, Error)]
+pub enum AuthError {
+    /// The socket could not be bound, connected, written to or read from.
+    #[error("Transport failure: {0}")]
+    Io(#[from] io::Error),
+
+    /// The server did not answer within the allowed number of attempts.
+    #[error("No response from server after {attempts} attempts!")]
+    Timeout { attempts: usize },
+
+    /// A datagram arrived but does not follow the protocol.
+    #[error("Malformed datagram: {0}")]
+    MalformedDatagram(#[from] DecodeError),
+
+    /// A well-formed datagram arrived but is not a reply to the request.
+    #[error("Invalid token type! Expected {expected:?}, got {found:?}.")]
+    UnexpectedMessage { expected: TokenType, found: TokenType },
+
+    /// The server answered with an error message (type 256).
+    #[error("Error: {0}")]
+    Server(ErrorCode),
+
+    /// The caller supplied an argument the protocol cannot carry.
+    #[error("{0}")]
+    InvalidInput(String),
+
+    /// A SAS or GAS supplied by the caller is not well formed.
+    #[error("{0}")]
+    InvalidToken(#[from] ParseTokenError),
+
+    /// The individual token of a group member could not be obtained.
+    #[error("Member {member}: {source}")]
+    Member { member: String, source: Box<AuthError> },
+}
+
+/// Why a SAS or GAS string could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseTokenError {
+    #[error("Incorrect number of SAS fields! Expected {expected}, got {found}.")]
+    FieldCount { expected: usize, found: usize },
+
+    #[error("ID is too long! Expected at most {max} bytes, got {found}.")]
+    IdTooLong { max: usize, found: usize },
+
+    #[error("ID must only contain ASCII characters!")]
+    NonAsciiId,
+
+    #[error("ID must not contain {0:?}, which separates the fields of a SAS or GAS!")]
+    IdSeparator(char),
+
+    #[error("Invalid nonce number: {0:?} is not a u32.")]
+    InvalidNonce(String),
+
+    #[error("Invalid token length! Expected {expected} hex characters, got {found}.")]
+    TokenLength { expected: usize, found: usize },
+
+    #[error("Invalid token character {0:?}! Expected a hex digit.")]
+    TokenNotHex(char),
+}
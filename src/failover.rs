@@ -0,0 +1,122 @@
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use crate::error::AuthError;
+use crate::retry::RetryPolicy;
+
+/// One operation tried on one server after another, for as long as none
+/// answers. The retry policy's deadline covers every server together, and a
+/// final timeout counts the attempts made on all of them.
+pub(crate) struct Failover {
+    policy: RetryPolicy,
+    deadline: Option<Duration>,
+    started: Instant,
+    servers: usize,
+    tried: usize,
+    attempts: usize,
+}
+
+impl Failover {
+    pub(crate) fn start(policy: &RetryPolicy, servers: usize) -> Self {
+        Self {
+            policy: policy.clone(),
+            deadline: policy.deadline,
+            started: Instant::now(),
+            servers: servers.max(1),
+            tried: 0,
+            attempts: 0,
+        }
+    }
+
+    /// How many servers were tried before the next one, and the retry policy
+    /// for it, or `None` once every server was tried or time ran out.
+    pub(crate) fn next_server(&mut self) -> Option<(usize, &RetryPolicy)> {
+        if self.tried == self.servers {
+            return None;
+        }
+
+        if let Some(deadline) = self.deadline {
+            let remaining = deadline.saturating_sub(self.started.elapsed());
+            if remaining.is_zero() {
+                return None;
+            }
+            self.policy.deadline = Some(remaining);
+        }
+
+        self.tried += 1;
+        Some((self.tried - 1, &self.policy))
+    }
+
+    /// Whether the server just tried never answered, so that the next one
+    /// should be.
+    pub(crate) fn unanswered<T>(&mut self, result: &Result<T, AuthError>) -> bool {
+        if let Err(AuthError::Timeout { attempts }) = result {
+            self.attempts += attempts;
+        }
+
+        unanswered(result)
+    }
+
+    /// The result of the last server tried.
+    pub(crate) fn finish<T>(self, result: Result<T, AuthError>) -> Result<T, AuthError> {
+        match result {
+            Err(AuthError::Timeout { .. }) => Err(AuthError::Timeout { attempts: self.attempts }),
+            result => result,
+        }
+    }
+}
+
+/// Whether a server never answered, so that another one should be tried.
+pub(crate) fn unanswered<T>(result: &Result<T, AuthError>) -> bool {
+    matches!(result, Err(AuthError::Timeout { .. } | AuthError::Io(_)))
+}
+
+/// Collects the addresses of every server, each server's interleaved and
+/// without repeats. Servers that cannot be resolved are skipped as long as
+/// one can.
+pub(crate) fn addresses(
+    servers: impl IntoIterator<Item = Result<Vec<SocketAddr>, Error>>,
+) -> Result<Vec<SocketAddr>, AuthError> {
+    let mut addresses: Vec<SocketAddr> = Vec::new();
+    let mut unresolved = None;
+
+    for server in servers {
+        match server {
+            Ok(resolved) => {
+                for address in interleave(resolved) {
+                    if !addresses.contains(&address) {
+                        addresses.push(address);
+                    }
+                }
+            }
+            Err(e) => unresolved = Some(e),
+        }
+    }
+
+    if addresses.is_empty() {
+        return Err(unresolved
+            .unwrap_or_else(|| Error::new(ErrorKind::InvalidInput, "No server address to connect to!"))
+            .into());
+    }
+
+    Ok(addresses)
+}
+
+/// Orders addresses so that IPv6 and IPv4 alternate, starting with the
+/// family of the first one, as happy eyeballs does.
+fn interleave(addresses: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_is_ipv6 = addresses.first().is_some_and(SocketAddr::is_ipv6);
+    let (mut preferred, mut other): (Vec<_>, Vec<_>) =
+        addresses.into_iter().partition(|address| address.is_ipv6() == first_is_ipv6);
+    let mut ordered = Vec::with_capacity(preferred.len() + other.len());
+
+    preferred.reverse();
+    other.reverse();
+    while !preferred.is_empty() || !other.is_empty() {
+        ordered.extend(preferred.pop());
+        ordered.extend(other.pop());
+    }
+
+    ordered
+}
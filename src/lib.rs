@@ -0,0 +1,21 @@
+#[cfg(feature = "async")]
+mod async_client;
+pub mod authentication;
+mod client;
+mod error;
+mod failover;
+mod retry;
+pub mod server;
+mod socket;
+
+#[cfg(feature = "async")]
+pub use async_client::AsyncClient;
+pub use authentication::check::{ErrorCode, TokenType};
+pub use authentication::gas::Gas;
+pub use authentication::package::{DecodeError, Message};
+pub use authentication::transaction::{Exchange, Failure};
+pub use authentication::sas::Sas;
+pub use client::Client;
+pub use error::{AuthError, ParseTokenError};
+pub use retry::{RetryPolicy, Schedule};
+pub use socket::{AddressFamily, SocketOptions};
@@ -1,52 +1,484 @@
-mod authentication;
+mod cli;
+mod config;
+mod output;
+mod repl;
+mod roster;
+mod store;
 
 use std::env;
-use std::net::UdpSocket;
-use std::time::Duration;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
 
-const EXPECTED_ARGUMENTS: usize = 4;
-const SOCKET_BIND_ADDRESS: &str = "[::]:0";
-const TIMEOUT_SECONDS: u64 = 5;
+use clap::{CommandFactory, Parser};
+use cli::{Cli, Command, ProtocolCommand, TokensCommand, DEFAULT_WINDOW};
+use config::Config;
+use output::{Format, Outcome, Report};
+use roster::Roster;
+use store::Store;
+use udp_auth_client::authentication::check::VALID_STATUS;
+use udp_auth_client::{AddressFamily, AuthError, Client, Exchange, Message, RetryPolicy, SocketOptions, TokenType};
+
+const STDIN_PATH: &str = "-";
+
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_INVALID_INPUT: i32 = 1;
+const EXIT_TRANSPORT: i32 = 2;
+const EXIT_TIMEOUT: i32 = 3;
+const EXIT_MALFORMED: i32 = 4;
+const EXIT_SERVER_ERROR: i32 = 5;
+
+/// The global options, as the commands use them.
+#[derive(Debug)]
+struct Options {
+    /// Servers in the order they are tried. Tokens are stored under the first.
+    servers: Vec<(String, u16)>,
+    socket: SocketOptions,
+    policy: RetryPolicy,
+    format: Format,
+    /// Batch commands in flight at once.
+    window: usize,
+    /// Answer `itr` from the token store when it holds a matching SAS.
+    use_cache: bool,
+}
+
+impl Options {
+    /// Settles every option: the positional server first, then flags and
+    /// their environment variables, then the selected profile, then the
+    /// built-in defaults.
+    fn resolve(cli: &Cli) -> Result<Self, AuthError> {
+        let options = &cli.options;
+        let profile = Config::load(options.config.as_deref())?.profile(options.profile.as_deref())?;
+        let mut policy = RetryPolicy::default();
+
+        if let Some(timeout) = options.timeout.or(profile.timeout()?) {
+            policy.initial_timeout = timeout;
+        }
+        if let Some(retries) = options.retries.or(profile.retries) {
+            policy.retries = retries;
+        }
+        policy.deadline = options.deadline.or(profile.deadline()?);
+
+        let host = cli.host.clone().or_else(|| options.server_host.clone()).or_else(|| profile.host.clone());
+        let port = cli.port.or(options.server_port).or(profile.port);
+        let mut servers: Vec<(String, u16)> = host.zip(port).into_iter().collect();
+
+        if options.servers.is_empty() {
+            servers.extend(profile.servers()?);
+        } else {
+            servers.extend(options.servers.iter().cloned());
+        }
+
+        let family = match (options.ipv4, options.ipv6) {
+            (true, _) => AddressFamily::Ipv4,
+            (_, true) => AddressFamily::Ipv6,
+            _ => AddressFamily::Any,
+        };
+
+        Ok(Self {
+            servers,
+            socket: SocketOptions {
+                family,
+                bind: options.bind.or(profile.bind),
+                interface: options.interface.clone().or(profile.interface),
+            },
+            policy,
+            format: options.output.or(profile.output).unwrap_or_default(),
+            window: options.window.or(profile.window).unwrap_or(DEFAULT_WINDOW),
+            use_cache: options.use_cache,
+        })
+    }
+
+    /// The first server as `<host>:<port>`, which keys the token store.
+    fn server(&self) -> Option<String> {
+        self.servers.first().map(|(host, port)| format!("{host}:{port}"))
+    }
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() < EXPECTED_ARGUMENTS {
-        eprintln!("Insufficient arguments! Expected at least {} arguments, but got {}.", EXPECTED_ARGUMENTS, args.len());
-        std::process::exit(1);
+    let cli = match Cli::try_parse_from(&args) {
+        Ok(cli) => cli,
+        Err(e) => usage_error(&args, &e),
+    };
+
+    let command = cli.command.name();
+    let options = match Options::resolve(&cli) {
+        Ok(options) => options,
+        Err(e) => fail(cli.options.output.unwrap_or_default(), Some(command), &e),
+    };
+
+    match run(cli.command, &options) {
+        Ok(EXIT_SUCCESS) => {}
+        Ok(code) => std::process::exit(code),
+        Err(e) => fail(options.format, Some(command), &e),
+    }
+}
+
+fn fail(format: Format, command: Option<&str>, error: &AuthError) -> ! {
+    let code = exit_code(error);
+
+    output::print_error(format, command, error, code);
+    std::process::exit(code);
+}
+
+/// Reports a command line that could not be parsed. Help and version
+/// requests are printed and succeed.
+fn usage_error(args: &[String], error: &clap::Error) -> ! {
+    if !error.use_stderr() {
+        error.exit();
+    }
+
+    let json = args.windows(2).any(|pair| pair[0] == "--output" && pair[1] == "json")
+        || args.iter().any(|arg| arg == "--output=json")
+        || env::var("UDP_AUTH_OUTPUT").is_ok_and(|format| format == "json");
+
+    if !json {
+        let _ = error.print();
+        std::process::exit(EXIT_INVALID_INPUT);
+    }
+
+    let cli = Cli::command();
+    let command = args.iter().skip(1).map(String::as_str).find(|arg| cli.find_subcommand(arg).is_some());
+
+    fail(Format::Json, command, &AuthError::InvalidInput(cli::message(error)))
+}
+
+fn exit_code(error: &AuthError) -> i32 {
+    match error {
+        AuthError::InvalidInput(_) | AuthError::InvalidToken(_) => EXIT_INVALID_INPUT,
+        AuthError::Io(_) => EXIT_TRANSPORT,
+        AuthError::Timeout { .. } => EXIT_TIMEOUT,
+        AuthError::MalformedDatagram(_) | AuthError::UnexpectedMessage { .. } => EXIT_MALFORMED,
+        AuthError::Server(_) => EXIT_SERVER_ERROR,
+        AuthError::Member { source, .. } => exit_code(source),
+    }
+}
+
+/// Runs the command line and returns the exit status for a completed run.
+fn run(command: Command, options: &Options) -> Result<i32, AuthError> {
+    let connect = || connect(options);
+
+    match command {
+        Command::Tokens { command } => tokens(options, &command),
+        Command::Batch { file } => {
+            let (client, server) = connect()?;
+            batch(&client, &server, options, &file)
+        }
+        Command::Interactive => {
+            let (client, server) = connect()?;
+            repl::run(&client, &server, options)
+        }
+        Command::Roster { file, out } => {
+            let (client, server) = connect()?;
+            enroll_roster(&client, &server, options, &file, out.as_deref())
+        }
+        Command::GroupEnroll { member, members_file } => {
+            let (client, server) = connect()?;
+            let report = group_enroll(&client, options, member, &members_file)?;
+            Ok(finish(&client, &server, options, &report))
+        }
+        Command::Protocol(command) => {
+            let request = command.into_request()?;
+
+            if let Some(report) = cached(options, &request) {
+                output::print(options.format, &report);
+                return Ok(EXIT_SUCCESS);
+            }
+
+            let (client, server) = connect()?;
+            let report = report(&request, client.transact(&request.message)?)?;
+            Ok(finish(&client, &server, options, &report))
+        }
+    }
+}
+
+/// Connects to the servers, returning the client and the `host:port` its
+/// tokens are stored under.
+fn connect(options: &Options) -> Result<(Client, String), AuthError> {
+    let Some(server) = options.server() else {
+        return Err(AuthError::InvalidInput(
+            "No server given! Pass <HOST> <PORT> before the command, --host and --port, --server or --profile."
+                .to_string(),
+        ));
+    };
+
+    let servers = options.servers.iter().map(|(host, port)| (host.as_str(), *port));
+    let client = Client::connect_with(servers, &options.socket)?.with_retry_policy(options.policy.clone());
+
+    Ok((client, server))
+}
+
+/// Stores and prints the result of a single command, noting on stderr when
+/// a server other than the first one answered it.
+fn finish(client: &Client, server: &str, options: &Options, report: &Report) -> i32 {
+    remember(server, [&report.outcome]);
+    output::print(options.format, report);
+
+    if let (Format::Text, Some(exchange)) = (options.format, &report.exchange) {
+        if client.servers().first() != Some(&exchange.server) {
+            eprintln!("Answered by {} after failover.", exchange.server);
+        }
+    }
+
+    EXIT_SUCCESS
+}
+
+/// Answers an `itr` request from the token store, with `--use-cache`,
+/// without resolving or contacting any server.
+fn cached(options: &Options, request: &Request) -> Option<Report> {
+    let Message::IndividualTokenRequest { id, nonce } = &request.message else {
+        return None;
+    };
+
+    if !options.use_cache {
+        return None;
+    }
+
+    let server = options.server()?;
+    let id = String::from_utf8_lossy(id);
+    let sas = Store::open().ok()?.find_sas(&server, id.trim_end_matches('\0'), *nonce)?;
+
+    Some(Report {
+        command: request.command.clone(),
+        outcome: Outcome::Sas(sas),
+        exchange: None,
+    })
+}
+
+/// Records the tokens of `outcomes` in the token store. A store that cannot
+/// be updated is reported, but does not fail the command.
+fn remember<'a>(server: &str, outcomes: impl IntoIterator<Item = &'a Outcome>) {
+    let updated = Store::update(|store| outcomes.into_iter().for_each(|outcome| store.record(server, outcome)));
+
+    if let Err(e) = updated {
+        eprintln!("Token store not updated: {e}");
+    }
+}
+
+/// Lists, shows or exports the token store. Needs no server.
+fn tokens(options: &Options, command: &TokensCommand) -> Result<i32, AuthError> {
+    let store = Store::open()?;
+
+    let entries: Vec<&store::Entry> = match command {
+        TokensCommand::List => store.tokens.iter().collect(),
+        TokensCommand::Show { id } => {
+            let found = store.find(id);
+
+            if found.is_empty() {
+                return Err(AuthError::InvalidInput(format!("No stored token for {id:?}!")));
+            }
+            found
+        }
+        TokensCommand::Export => {
+            println!("{}", serde_json::to_string_pretty(&store).expect("the store is always serializable"));
+            return Ok(EXIT_SUCCESS);
+        }
+    };
+
+    match options.format {
+        Format::Json => println!("{}", serde_json::to_string(&entries).expect("entries are always serializable")),
+        Format::Text => {
+            for entry in entries {
+                println!("{}\t{}\t{}\t{}", entry.server, entry.obtained_at, entry.status(), entry.token);
+            }
+        }
     }
 
-    let server_address = args.get(1).unwrap().as_ref();
-    let port = args.get(2).unwrap();
-    let command = args.get(3).unwrap();
+    Ok(EXIT_SUCCESS)
+}
+
+/// A command line turned into the message to send.
+struct Request {
+    command: String,
+    message: Message,
+    expected: TokenType,
+}
+
+/// Parses the words of a batch or shell line.
+fn parse_request(words: &[String]) -> Result<Request, AuthError> {
+    ProtocolCommand::parse_line(words)?.into_request()
+}
 
-    let port = match port.parse::<u16>() {
-        Ok(p) => p,
-        Err(e) => {
-            eprintln!("Invalid port number: {:?}", e.to_string());
-            std::process::exit(1);
+/// Interprets the reply that answered `request`.
+fn report(request: &Request, exchange: Exchange) -> Result<Report, AuthError> {
+    let outcome = match exchange.reply.clone() {
+        Message::IndividualTokenResponse(sas) => Outcome::Sas(sas),
+        Message::IndividualTokenStatus { sas, status } => Outcome::SasStatus {
+            sas,
+            valid: status == VALID_STATUS,
+        },
+        Message::GroupTokenResponse(gas) => Outcome::Gas(gas),
+        Message::GroupTokenStatus { gas, status } => Outcome::GasStatus {
+            gas,
+            valid: status == VALID_STATUS,
+        },
+        Message::ErrorMessage(error) => return Err(AuthError::Server(error)),
+        other => {
+            return Err(AuthError::UnexpectedMessage {
+                expected: request.expected,
+                found: other.token_type(),
+            })
         }
     };
 
-    let socket = UdpSocket::bind(SOCKET_BIND_ADDRESS).expect("Failed to bind socket to address");
-    let timeout_duration = Duration::new(TIMEOUT_SECONDS, 0);
-    
-    socket.set_read_timeout(Some(timeout_duration)).expect("Failed to set read timeout");
-    socket.set_write_timeout(Some(timeout_duration)).expect("Failed to set write timeout");
+    Ok(Report {
+        command: request.command.clone(),
+        outcome,
+        exchange: Some(exchange),
+    })
+}
+
+/// Runs one command per line of `path`, or of stdin for `-`, over the same
+/// socket. Blank lines and lines starting with `#` are skipped.
+///
+/// Up to `window` commands are in flight at once. Every command gets one line
+/// of output, in input order, and a failing command does not stop the batch.
+/// The exit status is that of the first failure.
+fn batch(client: &Client, server: &str, options: &Options, path: &str) -> Result<i32, AuthError> {
+    let mut lines = Vec::new();
+
+    for (index, line) in read_lines(path)?.into_iter().enumerate() {
+        let words: Vec<String> = line.split_whitespace().map(String::from).collect();
+
+        match words.split_first() {
+            Some((command, _)) if command.starts_with('#') => {}
+            Some((command, _)) => lines.push((index + 1, command.clone(), parse_request(&words))),
+            None => {}
+        }
+    }
+
+    let messages: Vec<Message> = lines
+        .iter()
+        .filter_map(|(_, _, request)| request.as_ref().ok())
+        .map(|request| request.message.clone())
+        .collect();
 
-    if let Err(e) = socket.connect((server_address, port)) {
-        eprintln!("Failed to connect to the server: {:?}", e.to_string());
-        std::process::exit(1);
+    let mut exchanges = client.transact_all(&messages, options.window).into_iter();
+    let mut status = EXIT_SUCCESS;
+    let mut results = Vec::with_capacity(lines.len());
+
+    for (line, command, request) in lines {
+        let result = request.and_then(|request| {
+            let exchange = exchanges.next().expect("one exchange per request")?;
+            report(&request, exchange)
+        });
+        results.push((line, command, result));
     }
 
-    match command.as_str() {
-        "itr" => authentication::sas::itr(&socket, &args[EXPECTED_ARGUMENTS..]),
-        "itv" => authentication::sas::itv(&socket, &args[EXPECTED_ARGUMENTS..]),
-        "gtr" => authentication::gas::gtr(&socket, &args[EXPECTED_ARGUMENTS..]),
-        "gtv" => authentication::gas::gtv(&socket, &args[EXPECTED_ARGUMENTS..]),
-        _ => {
-            eprintln!("Unknown command: {}", command);
-            std::process::exit(1);
+    remember(server, results.iter().filter_map(|(_, _, result)| result.as_ref().ok()).map(|report| &report.outcome));
+
+    for (line, command, result) in results {
+        if let Err(e) = &result {
+            if status == EXIT_SUCCESS {
+                status = exit_code(e);
+            }
         }
+
+        output::print_batch_line(options.format, line, &command, &result, exit_code);
+    }
+
+    Ok(status)
+}
+
+/// Requests the individual token of every member, all in flight at once,
+/// then the group token covering them.
+///
+/// Members come from `--member <id>:<nonce>` flags and from the lines of any
+/// `--members-file <file>`. No group token is requested unless every member got
+/// its individual token.
+fn group_enroll(
+    client: &Client,
+    options: &Options,
+    mut members: Vec<(String, u32)>,
+    files: &[String],
+) -> Result<Report, AuthError> {
+    for path in files {
+        for line in read_lines(path)? {
+            let line = line.trim();
+
+            if !line.is_empty() && !line.starts_with('#') {
+                members.push(cli::parse_member(line)?);
+            }
+        }
+    }
+
+    if members.is_empty() {
+        return Err(AuthError::InvalidInput(
+            "No members given! Use --member <id>:<nonce> or --members-file <file>.".to_string(),
+        ));
+    }
+
+    let requests: Vec<(&str, u32)> = members.iter().map(|(id, nonce)| (id.as_str(), *nonce)).collect();
+    let tokens = client.request_individual_tokens(&requests, options.window);
+
+    let members = members
+        .iter()
+        .zip(tokens)
+        .map(|((id, nonce), sas)| {
+            sas.map_err(|e| AuthError::Member {
+                member: format!("{id}:{nonce}"),
+                source: Box::new(e),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let request = Request {
+        command: "group-enroll".to_string(),
+        message: Message::GroupTokenRequest(members),
+        expected: TokenType::GroupTokenResponse,
+    };
+    let exchange = client.transact(&request.message)?;
+
+    report(&request, exchange)
+}
+
+/// Obtains every SAS and GAS of a roster file and writes them as CSV, or as
+/// JSON with `--output json`. With `--out <file>` they go to that file
+/// instead of stdout, in the format named by its extension.
+///
+/// The exit status is that of the first failure.
+fn enroll_roster(client: &Client, server: &str, options: &Options, path: &str, out: Option<&str>) -> Result<i32, AuthError> {
+    let json = match out {
+        Some(out) => roster::extension(out).as_deref() == Some("json"),
+        None => options.format == Format::Json,
+    };
+
+    let enrollments = Roster::load(path)?.enroll(client, options.window);
+    remember(server, &enrollments.iter().flat_map(roster::Enrollment::outcomes).collect::<Vec<_>>());
+
+    let document = if json {
+        format!("{:#}\n", roster::to_json(&enrollments)).into_bytes()
+    } else {
+        let mut csv = Vec::new();
+        roster::write_csv(&mut csv, &enrollments).map_err(|e| AuthError::Io(e.into()))?;
+        csv
+    };
+
+    match out {
+        Some(out) => fs::write(out, document).map_err(|e| AuthError::InvalidInput(format!("Cannot write {out:?}: {e}")))?,
+        None => io::stdout().write_all(&document)?,
     }
+
+    let status = enrollments
+        .iter()
+        .find_map(|enrollment| enrollment.error())
+        .map_or(EXIT_SUCCESS, exit_code);
+
+    Ok(status)
+}
+
+/// Reads every line of `path`, or of stdin for `-`.
+fn read_lines(path: &str) -> Result<Vec<String>, AuthError> {
+    let input: Box<dyn BufRead> = if path == STDIN_PATH {
+        Box::new(io::stdin().lock())
+    } else {
+        let file = File::open(path).map_err(|e| AuthError::InvalidInput(format!("Cannot open {path:?}: {e}")))?;
+        Box::new(BufReader::new(file))
+    };
+
+    input
+        .lines()
+        .collect::<Result<_, _>>()
+        .map_err(|e| AuthError::InvalidInput(format!("Cannot read {path:?}: {e}")))
 }
@@ -0,0 +1,171 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use udp_auth_client::{AuthError, Exchange, Gas, Sas};
+
+/// How command results and errors are printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+}
+
+/// What a command got back from the server.
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    Sas(Sas),
+    Gas(Gas),
+    SasStatus { sas: Sas, valid: bool },
+    GasStatus { gas: Gas, valid: bool },
+}
+
+/// A finished command, ready to be printed.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub command: String,
+    pub outcome: Outcome,
+    /// `None` when the result came from the token store.
+    pub exchange: Option<Exchange>,
+}
+
+pub fn print(format: Format, report: &Report) {
+    match format {
+        Format::Text => println!("{}", text(&report.outcome)),
+        Format::Json => println!("{}", json(report)),
+    }
+}
+
+pub fn print_error(format: Format, command: Option<&str>, error: &AuthError, exit_code: i32) {
+    match format {
+        Format::Text => eprintln!("{error}"),
+        Format::Json => println!("{}", error_json(command, error, exit_code)),
+    }
+}
+
+/// Prints the result of one line of a batch. Failures are printed in place,
+/// on stdout, so that every input line gets exactly one output line.
+pub fn print_batch_line(
+    format: Format,
+    line: usize,
+    command: &str,
+    result: &Result<Report, AuthError>,
+    exit_code: fn(&AuthError) -> i32,
+) {
+    match (format, result) {
+        (Format::Text, Ok(report)) => println!("{}", text(&report.outcome)),
+        (Format::Text, Err(error)) => println!("line {line}: {error}"),
+        (Format::Json, _) => {
+            let value = match result {
+                Ok(report) => json(report),
+                Err(error) => error_json(Some(command), error, exit_code(error)),
+            };
+
+            let mut object = Map::new();
+            object.insert("line".into(), json!(line));
+
+            if let Value::Object(fields) = value {
+                object.extend(fields);
+            }
+
+            println!("{}", Value::Object(object));
+        }
+    }
+}
+
+fn text(outcome: &Outcome) -> String {
+    match outcome {
+        Outcome::Sas(sas) => sas.to_string(),
+        Outcome::Gas(gas) => gas.to_string(),
+        Outcome::SasStatus { valid, .. } | Outcome::GasStatus { valid, .. } => (!valid as u8).to_string(),
+    }
+}
+
+fn json(report: &Report) -> Value {
+    let mut object = Map::new();
+    object.insert("command".into(), json!(report.command));
+
+    match &report.outcome {
+        Outcome::Sas(sas) => {
+            object.extend(sas_fields(sas));
+            object.insert("sas".into(), json!(sas.to_string()));
+        }
+        Outcome::Gas(gas) => {
+            object.insert("members".into(), members(gas));
+            object.insert("token".into(), json!(gas.token_str()));
+            object.insert("gas".into(), json!(gas.to_string()));
+        }
+        Outcome::SasStatus { sas, valid } => {
+            object.insert("id".into(), json!(sas.id_str()));
+            object.insert("nonce".into(), json!(sas.nonce));
+            object.insert("valid".into(), json!(valid));
+        }
+        Outcome::GasStatus { gas, valid } => {
+            object.insert("members".into(), members(gas));
+            object.insert("valid".into(), json!(valid));
+        }
+    }
+
+    match &report.exchange {
+        Some(exchange) => {
+            object.insert("server".into(), json!(exchange.server.to_string()));
+            object.insert("rtt_ms".into(), json!(exchange.rtt.as_secs_f64() * 1000.0));
+            object.insert("attempts".into(), json!(exchange.attempts));
+        }
+        None => {
+            object.insert("cached".into(), json!(true));
+        }
+    }
+
+    Value::Object(object)
+}
+
+fn error_json(command: Option<&str>, error: &AuthError, exit_code: i32) -> Value {
+    let mut details = Map::new();
+    details.insert("kind".into(), json!(error_kind(error)));
+    details.insert("message".into(), json!(error.to_string()));
+    details.insert("exit_code".into(), json!(exit_code));
+
+    let mut cause = error;
+    if let AuthError::Member { member, source } = error {
+        details.insert("member".into(), json!(member));
+        cause = source;
+    }
+
+    match cause {
+        AuthError::Server(code) => {
+            details.insert("code".into(), json!(code.code()));
+            details.insert("description".into(), json!(code.description()));
+        }
+        AuthError::Timeout { attempts } => {
+            details.insert("attempts".into(), json!(attempts));
+        }
+        _ => {}
+    }
+
+    json!({ "command": command, "error": details })
+}
+
+fn error_kind(error: &AuthError) -> &'static str {
+    match error {
+        AuthError::InvalidInput(_) | AuthError::InvalidToken(_) => "invalid_input",
+        AuthError::Io(_) => "transport",
+        AuthError::Timeout { .. } => "timeout",
+        AuthError::MalformedDatagram(_) | AuthError::UnexpectedMessage { .. } => "malformed",
+        AuthError::Server(_) => "server",
+        AuthError::Member { source, .. } => error_kind(source),
+    }
+}
+
+fn sas_fields(sas: &Sas) -> Map<String, Value> {
+    let mut fields = Map::new();
+    fields.insert("id".into(), json!(sas.id_str()));
+    fields.insert("nonce".into(), json!(sas.nonce));
+    fields.insert("token".into(), json!(sas.token_str()));
+    fields
+}
+
+fn members(gas: &Gas) -> Value {
+    gas.members.iter().map(|sas| Value::Object(sas_fields(sas))).collect()
+}
@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Error;
+
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use udp_auth_client::{AuthError, Client, Exchange, Failure, Message};
+
+use crate::output::{self, Format, Outcome};
+use crate::{parse_request, remember, report, store, Options, EXIT_SUCCESS};
+
+const PROMPT: &str = "udp-auth> ";
+const HISTORY_FILE: &str = "history";
+
+const HELP: &str = "\
+Commands:
+  itr <id> <nonce>               Request an individual token
+  itv <SAS>                      Validate an individual token
+  gtr <N> <SAS-1> ... <SAS-N>    Request a group token
+  gtv <GAS>                      Validate a group token
+  vars                           List the variables
+  last                           Show the raw bytes, timing and server of the last request
+  help                           Show this help
+  quit                           Leave the shell
+
+Every SAS and GAS obtained is kept as $1, $2, ... and every SAS also as
+$<id>, so `gtr 2 $alice $bob` reuses earlier results.";
+
+/// An interactive session over one connected client.
+struct Session<'a> {
+    client: &'a Client,
+    server: &'a str,
+    options: &'a Options,
+    results: Vec<String>,
+    names: BTreeMap<String, String>,
+    last: Option<Last>,
+}
+
+/// The last request sent, and its exchange or why it failed.
+struct Last {
+    request: Message,
+    outcome: Result<Exchange, (Failure, String)>,
+}
+
+/// Reads commands until end of input or `quit`, with line editing and a
+/// history kept next to the token store.
+pub fn run(client: &Client, server: &str, options: &Options) -> Result<i32, AuthError> {
+    let mut editor = DefaultEditor::new().map_err(readline_error)?;
+    let history = store::data_directory().map(|directory| directory.join(HISTORY_FILE));
+
+    if let Ok(history) = &history {
+        let _ = editor.load_history(history);
+    }
+
+    let mut session = Session {
+        client,
+        server,
+        options,
+        results: Vec::new(),
+        names: BTreeMap::new(),
+        last: None,
+    };
+
+    loop {
+        let line = match editor.readline(PROMPT) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(readline_error(e)),
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let _ = editor.add_history_entry(line);
+
+        match session.execute(line) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+
+    if let Ok(history) = &history {
+        if history.parent().is_some_and(|directory| fs::create_dir_all(directory).is_ok()) {
+            let _ = editor.save_history(history);
+        }
+    }
+
+    Ok(EXIT_SUCCESS)
+}
+
+impl Session<'_> {
+    /// Runs one line, returning `false` once the session should end.
+    fn execute(&mut self, line: &str) -> Result<bool, AuthError> {
+        let words = line.split_whitespace().map(|word| self.substitute(word)).collect::<Result<Vec<_>, _>>()?;
+        let command = words.first().expect("the line is not empty");
+
+        match command.as_str() {
+            "quit" | "exit" => return Ok(false),
+            "help" => println!("{HELP}"),
+            "vars" => self.print_variables(),
+            "last" => self.print_last()?,
+            _ => {
+                let request = parse_request(&words)?;
+                let exchange = self.client.transact(&request.message);
+
+                let outcome = match &exchange {
+                    Ok(exchange) => Some(Ok(exchange.clone())),
+                    Err(e) => self.client.last_failure().map(|failure| Err((failure, e.to_string()))),
+                };
+                if let Some(outcome) = outcome {
+                    self.last = Some(Last {
+                        request: request.message.clone(),
+                        outcome,
+                    });
+                }
+
+                let report = report(&request, exchange?)?;
+                remember(self.server, [&report.outcome]);
+
+                let variable = self.keep(&report.outcome);
+                if let (Some(variable), Format::Text) = (variable, self.options.format) {
+                    print!("{variable} = ");
+                }
+                output::print(self.options.format, &report);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Replaces a `$name` or `$n` word with the value it refers to.
+    fn substitute(&self, word: &str) -> Result<String, AuthError> {
+        let Some(name) = word.strip_prefix('$') else {
+            return Ok(word.to_string());
+        };
+
+        let value = match name.parse::<usize>() {
+            Ok(index) => index.checked_sub(1).and_then(|index| self.results.get(index)),
+            Err(_) => self.names.get(name),
+        };
+
+        value
+            .cloned()
+            .ok_or_else(|| AuthError::InvalidInput(format!("Unknown variable {word}!")))
+    }
+
+    /// Stores an obtained token in the next numbered variable, and a SAS
+    /// under its ID too.
+    fn keep(&mut self, outcome: &Outcome) -> Option<String> {
+        let value = match outcome {
+            Outcome::Sas(sas) => {
+                self.names.insert(sas.id_str().into_owned(), sas.to_string());
+                sas.to_string()
+            }
+            Outcome::Gas(gas) => gas.to_string(),
+            Outcome::SasStatus { .. } | Outcome::GasStatus { .. } => return None,
+        };
+
+        self.results.push(value);
+        Some(format!("${}", self.results.len()))
+    }
+
+    fn print_variables(&self) {
+        for (index, value) in self.results.iter().enumerate() {
+            println!("${} = {value}", index + 1);
+        }
+        for (name, value) in &self.names {
+            println!("${name} = {value}");
+        }
+    }
+
+    /// Prints the datagrams of the last request as they went over the wire,
+    /// even when it timed out or its reply could not be decoded.
+    fn print_last(&self) -> Result<(), AuthError> {
+        let Last { request, outcome } = self
+            .last
+            .as_ref()
+            .ok_or_else(|| AuthError::InvalidInput("No exchange yet!".to_string()))?;
+
+        match outcome {
+            Ok(exchange) => {
+                println!("request  {:?} ({} bytes): {}", request.token_type(), exchange.sent.len(), hex(&exchange.sent));
+                println!(
+                    "reply    {:?} ({} bytes): {}",
+                    exchange.reply.token_type(),
+                    exchange.received.len(),
+                    hex(&exchange.received)
+                );
+                println!("attempts {}", exchange.attempts);
+                println!("rtt      {:.3} ms", exchange.rtt.as_secs_f64() * 1000.0);
+                println!("server   {}", exchange.server);
+            }
+            Err((failure, error)) => {
+                println!("request  {:?} ({} bytes): {}", request.token_type(), failure.sent.len(), hex(&failure.sent));
+                match &failure.received {
+                    Some(received) => println!("reply    malformed ({} bytes): {}", received.len(), hex(received)),
+                    None => println!("reply    none"),
+                }
+                println!("error    {error}");
+                println!("server   {}", failure.server);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect::<Vec<_>>().join(" ")
+}
+
+fn readline_error(error: ReadlineError) -> AuthError {
+    match error {
+        ReadlineError::Io(e) => AuthError::Io(e),
+        other => AuthError::Io(Error::other(other.to_string())),
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+const DEFAULT_TIMEOUT_SECONDS: u64 = 5;
+const DEFAULT_MAX_TIMEOUT_SECONDS: u64 = 20;
+const DEFAULT_RETRIES: usize = 2;
+const DEFAULT_MULTIPLIER: f64 = 2.0;
+const DEFAULT_JITTER: f64 = 0.1;
+/// How far ahead a wait too long for an `Instant` is put off instead.
+const FAR_FUTURE: Duration = Duration::from_secs(100 * 365 * 24 * 60 * 60);
+
+/// How long to wait for a reply and how often to retransmit.
+///
+/// Attempt `n` (starting at 0) waits `initial_timeout * multiplier^n`, capped
+/// at `max_timeout` (or at `initial_timeout` when that is longer) and scaled
+/// by a random factor in `1 ± jitter`. No attempt is started, or waited on,
+/// past the overall `deadline`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub initial_timeout: Duration,
+    pub multiplier: f64,
+    pub max_timeout: Duration,
+    pub jitter: f64,
+    /// Retransmissions after the first attempt.
+    pub retries: usize,
+    pub deadline: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECONDS),
+            multiplier: DEFAULT_MULTIPLIER,
+            max_timeout: Duration::from_secs(DEFAULT_MAX_TIMEOUT_SECONDS),
+            jitter: DEFAULT_JITTER,
+            retries: DEFAULT_RETRIES,
+            deadline: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn attempts(&self) -> usize {
+        self.retries + 1
+    }
+
+    /// Time to wait for a reply to attempt `attempt`, before the deadline.
+    pub fn timeout(&self, attempt: usize) -> Duration {
+        let backoff = self.multiplier.max(1.0).powi(attempt.min(i32::MAX as usize) as i32);
+        let cap = self.max_timeout.max(self.initial_timeout);
+        let base = Duration::try_from_secs_f64(self.initial_timeout.as_secs_f64() * backoff)
+            .map_or(cap, |base| base.min(cap));
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        Duration::try_from_secs_f64(base.as_secs_f64() * (1.0 + jitter * (2.0 * random_unit() - 1.0)))
+            .unwrap_or(Duration::MAX)
+    }
+
+    /// Starts the clock for one request.
+    pub fn start(&self) -> Schedule<'_> {
+        Schedule {
+            policy: self,
+            started: Instant::now(),
+            attempt: 0,
+        }
+    }
+}
+
+/// Attempt timeouts for a single request under a [`RetryPolicy`].
+pub struct Schedule<'a> {
+    policy: &'a RetryPolicy,
+    started: Instant,
+    attempt: usize,
+}
+
+impl Schedule<'_> {
+    /// Timeout for the next attempt, or `None` once attempts or time ran out.
+    pub fn next_timeout(&mut self) -> Option<Duration> {
+        if self.attempt >= self.policy.attempts() {
+            return None;
+        }
+
+        let mut timeout = self.policy.timeout(self.attempt);
+
+        if let Some(deadline) = self.policy.deadline {
+            let remaining = deadline.saturating_sub(self.started.elapsed());
+            if remaining.is_zero() {
+                return None;
+            }
+            timeout = timeout.min(remaining);
+        }
+
+        self.attempt += 1;
+        Some(timeout)
+    }
+
+    /// Attempts started so far.
+    pub fn attempts(&self) -> usize {
+        self.attempt
+    }
+}
+
+/// The instant `duration` after `start`, or [`FAR_FUTURE`] after it when
+/// that cannot be represented.
+pub(crate) fn later(start: Instant, duration: Duration) -> Instant {
+    start.checked_add(duration).unwrap_or_else(|| start + FAR_FUTURE)
+}
+
+/// Uniform value in `[0, 1)` from the standard library's random hash keys.
+fn random_unit() -> f64 {
+    let bits = RandomState::new().build_hasher().finish() >> 11;
+    bits as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,217 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use udp_auth_client::{AuthError, Client, Gas, Sas};
+
+use crate::output::Outcome;
+
+/// Groups and their members, as kept in a course spreadsheet.
+///
+/// A TOML roster lists `[[group]]` tables, each with a `name` and a
+/// `members` array of `{ id, nonce }` tables. A CSV roster has a header and
+/// one `group,id,nonce` row per member; rows of the same group need not be
+/// adjacent.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Roster {
+    #[serde(rename = "group", default)]
+    pub groups: Vec<Group>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Group {
+    pub name: String,
+    #[serde(default)]
+    pub members: Vec<Member>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Member {
+    pub id: String,
+    pub nonce: u32,
+}
+
+#[derive(Deserialize)]
+struct Row {
+    group: String,
+    id: String,
+    nonce: u32,
+}
+
+/// Every token obtained for one group of a roster.
+#[derive(Debug)]
+pub struct Enrollment {
+    pub name: String,
+    pub members: Vec<(Member, Result<Sas, AuthError>)>,
+    /// `None` when a member's SAS could not be obtained.
+    pub gas: Option<Result<Gas, AuthError>>,
+}
+
+impl Roster {
+    /// Reads a roster, choosing the format from the file extension. Every
+    /// group must have a member, since no group token covers none.
+    pub fn load(path: &str) -> Result<Self, AuthError> {
+        let text = fs::read_to_string(path).map_err(|e| AuthError::InvalidInput(format!("Cannot read {path:?}: {e}")))?;
+        let invalid = |e: &dyn std::fmt::Display| AuthError::InvalidInput(format!("Invalid roster {path:?}: {e}"));
+
+        let roster: Self = match extension(path).as_deref() {
+            Some("toml") => toml::from_str(&text).map_err(|e| invalid(&e))?,
+            Some("csv") => Self::from_csv(&text).map_err(|e| invalid(&e))?,
+            _ => {
+                return Err(AuthError::InvalidInput(format!(
+                    "Unknown roster format for {path:?}! Expected a .toml or .csv file."
+                )))
+            }
+        };
+
+        match roster.groups.iter().find(|group| group.members.is_empty()) {
+            Some(empty) => Err(invalid(&format_args!("group {:?} has no members.", empty.name))),
+            None => Ok(roster),
+        }
+    }
+
+    fn from_csv(text: &str) -> Result<Self, csv::Error> {
+        let mut roster = Self::default();
+
+        for row in csv::Reader::from_reader(text.as_bytes()).deserialize() {
+            let Row { group, id, nonce } = row?;
+            let member = Member { id, nonce };
+
+            match roster.groups.iter_mut().find(|existing| existing.name == group) {
+                Some(existing) => existing.members.push(member),
+                None => roster.groups.push(Group {
+                    name: group,
+                    members: vec![member],
+                }),
+            }
+        }
+
+        Ok(roster)
+    }
+
+    /// Requests the SAS of every member of every group, then the GAS of
+    /// every group whose members all got theirs. Up to `window` requests are
+    /// in flight at once.
+    pub fn enroll(self, client: &Client, window: usize) -> Vec<Enrollment> {
+        let requests: Vec<(&str, u32)> = self
+            .groups
+            .iter()
+            .flat_map(|group| &group.members)
+            .map(|member| (member.id.as_str(), member.nonce))
+            .collect();
+
+        let mut tokens = client.request_individual_tokens(&requests, window).into_iter();
+
+        let mut enrollments: Vec<Enrollment> = self
+            .groups
+            .into_iter()
+            .map(|group| Enrollment {
+                name: group.name,
+                members: group.members.into_iter().zip(tokens.by_ref()).collect(),
+                gas: None,
+            })
+            .collect();
+
+        let complete: Vec<usize> = (0..enrollments.len())
+            .filter(|index| enrollments[*index].members.iter().all(|(_, sas)| sas.is_ok()))
+            .collect();
+
+        let groups: Vec<Vec<Sas>> = complete
+            .iter()
+            .map(|index| enrollments[*index].members.iter().filter_map(|(_, sas)| sas.as_ref().ok()).cloned().collect())
+            .collect();
+
+        for (index, gas) in complete.into_iter().zip(client.request_group_tokens(&groups, window)) {
+            enrollments[index].gas = Some(gas);
+        }
+
+        enrollments
+    }
+}
+
+impl Enrollment {
+    /// The first failure in this group, if any.
+    pub fn error(&self) -> Option<&AuthError> {
+        self.members
+            .iter()
+            .find_map(|(_, sas)| sas.as_ref().err())
+            .or_else(|| self.gas.as_ref().and_then(|gas| gas.as_ref().err()))
+    }
+
+    /// The tokens obtained for this group.
+    pub fn outcomes(&self) -> Vec<Outcome> {
+        let members = self.members.iter().filter_map(|(_, sas)| sas.as_ref().ok()).cloned().map(Outcome::Sas);
+        let gas = self.gas.iter().filter_map(|gas| gas.as_ref().ok()).cloned().map(Outcome::Gas);
+
+        members.chain(gas).collect()
+    }
+
+    fn gas_string(&self) -> String {
+        match &self.gas {
+            Some(Ok(gas)) => gas.to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn gas_error(&self) -> Option<String> {
+        match &self.gas {
+            Some(Ok(_)) => None,
+            Some(Err(e)) => Some(e.to_string()),
+            None => Some("Group token not requested: a member failed!".to_string()),
+        }
+    }
+}
+
+/// Writes one `group,id,nonce,sas,gas,error` row per member.
+pub fn write_csv<W: Write>(output: W, enrollments: &[Enrollment]) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_writer(output);
+    writer.write_record(["group", "id", "nonce", "sas", "gas", "error"])?;
+
+    for enrollment in enrollments {
+        let gas = enrollment.gas_string();
+
+        for (member, sas) in &enrollment.members {
+            let (sas, error) = match sas {
+                Ok(sas) => (sas.to_string(), enrollment.gas_error().unwrap_or_default()),
+                Err(e) => (String::new(), e.to_string()),
+            };
+
+            writer.write_record([&enrollment.name, &member.id, &member.nonce.to_string(), &sas, &gas, &error])?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Describes every group, its members and its GAS.
+pub fn to_json(enrollments: &[Enrollment]) -> Value {
+    let groups: Vec<Value> = enrollments
+        .iter()
+        .map(|enrollment| {
+            let members: Vec<Value> = enrollment
+                .members
+                .iter()
+                .map(|(member, sas)| match sas {
+                    Ok(sas) => json!({ "id": member.id, "nonce": member.nonce, "sas": sas.to_string() }),
+                    Err(e) => json!({ "id": member.id, "nonce": member.nonce, "error": e.to_string() }),
+                })
+                .collect();
+
+            let mut group = json!({ "name": enrollment.name, "members": members });
+            match enrollment.gas_error() {
+                None => group["gas"] = json!(enrollment.gas_string()),
+                Some(error) => group["error"] = json!(error),
+            }
+            group
+        })
+        .collect();
+
+    json!({ "groups": groups })
+}
+
+pub fn extension(path: &str) -> Option<String> {
+    Path::new(path).extension().map(|extension| extension.to_string_lossy().to_lowercase())
+}
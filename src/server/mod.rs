@@ -0,0 +1,133 @@
+mod token;
+
+use std::io::Error;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+use crate::authentication::check::{ErrorCode, INVALID_STATUS, VALID_STATUS};
+use crate::authentication::gas::Gas;
+use crate::authentication::package::{DecodeError, Message, MAX_DATAGRAM_SIZE};
+use crate::authentication::sas::Sas;
+
+pub use token::TokenIssuer;
+
+/// Reference implementation of the authentication server.
+///
+/// Requests are decoded with the same [`Message`] codec the client uses, so
+/// both sides always agree on the wire format.
+pub struct Server {
+    socket: UdpSocket,
+    tokens: TokenIssuer,
+}
+
+impl Server {
+    pub fn bind<A: ToSocketAddrs>(address: A, tokens: TokenIssuer) -> Result<Self, Error> {
+        Ok(Self::new(UdpSocket::bind(address)?, tokens))
+    }
+
+    pub fn new(socket: UdpSocket, tokens: TokenIssuer) -> Self {
+        Self { socket, tokens }
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.socket.local_addr()
+    }
+
+    /// Answers requests until receiving from the socket fails.
+    pub fn run(&self) -> Result<(), Error> {
+        let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+
+        loop {
+            let (received, peer) = self.socket.recv_from(&mut buf)?;
+
+            let reply = if received == buf.len() {
+                Message::ErrorMessage(ErrorCode::IncorrectMessageLength)
+            } else {
+                self.handle(&buf[..received])
+            };
+
+            // A peer that went away must not take the server down with it
+            let _ = self.socket.send_to(&reply.encode(), peer);
+        }
+    }
+
+    /// Computes the reply to a single datagram.
+    pub fn handle(&self, datagram: &[u8]) -> Message {
+        self.process(datagram).unwrap_or_else(Message::ErrorMessage)
+    }
+
+    fn process(&self, datagram: &[u8]) -> Result<Message, ErrorCode> {
+        let request = Message::decode(datagram).map_err(|e| match e {
+            DecodeError::IncorrectMessageLength { .. } | DecodeError::Truncated { .. } => {
+                ErrorCode::IncorrectMessageLength
+            }
+            DecodeError::AsciiDecodeError => ErrorCode::AsciiDecodeError,
+            DecodeError::InvalidMessageCode(_) => ErrorCode::InvalidMessageCode,
+        })?;
+
+        match request {
+            Message::IndividualTokenRequest { id, nonce } => {
+                let token = self.tokens.sas_token(&id, nonce);
+                Ok(Message::IndividualTokenResponse(Sas { id, nonce, token }))
+            }
+            Message::IndividualTokenValidation(sas) => {
+                check_sas(&sas)?;
+
+                let status = self.status(self.is_valid_sas(&sas));
+                Ok(Message::IndividualTokenStatus { sas, status })
+            }
+            Message::GroupTokenRequest(members) => {
+                check_members(&members)?;
+
+                if !members.iter().all(|sas| self.is_valid_sas(sas)) {
+                    return Err(ErrorCode::InvalidSingleToken);
+                }
+
+                let token = self.tokens.gas_token(&members);
+                Ok(Message::GroupTokenResponse(Gas { members, token }))
+            }
+            Message::GroupTokenValidation(gas) => {
+                check_members(&gas.members)?;
+                check_token(&gas.token)?;
+
+                let valid = gas.members.iter().all(|sas| self.is_valid_sas(sas))
+                    && gas.token == self.tokens.gas_token(&gas.members);
+
+                let status = self.status(valid);
+                Ok(Message::GroupTokenStatus { gas, status })
+            }
+            _ => Err(ErrorCode::InvalidMessageCode),
+        }
+    }
+
+    fn is_valid_sas(&self, sas: &Sas) -> bool {
+        sas.token == self.tokens.sas_token(&sas.id, sas.nonce)
+    }
+
+    fn status(&self, valid: bool) -> u8 {
+        if valid {
+            VALID_STATUS
+        } else {
+            INVALID_STATUS
+        }
+    }
+}
+
+fn check_token(token: &[u8]) -> Result<(), ErrorCode> {
+    if token.iter().all(u8::is_ascii_hexdigit) {
+        Ok(())
+    } else {
+        Err(ErrorCode::InvalidParameter)
+    }
+}
+
+fn check_sas(sas: &Sas) -> Result<(), ErrorCode> {
+    check_token(&sas.token)
+}
+
+fn check_members(members: &[Sas]) -> Result<(), ErrorCode> {
+    if members.is_empty() {
+        return Err(ErrorCode::InvalidParameter);
+    }
+
+    members.iter().try_for_each(check_sas)
+}
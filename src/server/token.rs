@@ -0,0 +1,67 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+
+use crate::authentication::sas::{Sas, ID_LEN, TOKEN_LEN};
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+const RANDOM_SECRET_WORDS: usize = 4;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Issues tokens as hex HMAC-SHA-256 digests keyed by a server secret.
+///
+/// A SAS token covers the 12-byte ID and the nonce, a GAS token covers the
+/// member SAS blocks exactly as they appear on the wire. Tokens are therefore
+/// reproducible for a given secret and validating one just means issuing it
+/// again and comparing.
+pub struct TokenIssuer {
+    secret: Vec<u8>,
+}
+
+impl TokenIssuer {
+    pub fn new(secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            secret: secret.as_ref().to_vec(),
+        }
+    }
+
+    /// Uses a secret that changes every time the process starts.
+    pub fn random() -> Self {
+        let secret: Vec<u8> = (0..RANDOM_SECRET_WORDS)
+            .flat_map(|_| RandomState::new().build_hasher().finish().to_be_bytes())
+            .collect();
+
+        Self { secret }
+    }
+
+    pub fn sas_token(&self, id: &[u8; ID_LEN], nonce: u32) -> [u8; TOKEN_LEN] {
+        self.token(&[id, &nonce.to_be_bytes()])
+    }
+
+    pub fn gas_token(&self, members: &[Sas]) -> [u8; TOKEN_LEN] {
+        let nonces: Vec<[u8; 4]> = members.iter().map(|sas| sas.nonce.to_be_bytes()).collect();
+        let parts: Vec<&[u8]> = members
+            .iter()
+            .zip(&nonces)
+            .flat_map(|(sas, nonce)| [&sas.id[..], &nonce[..], &sas.token[..]])
+            .collect();
+
+        self.token(&parts)
+    }
+
+    fn token(&self, parts: &[&[u8]]) -> [u8; TOKEN_LEN] {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        parts.iter().for_each(|part| mac.update(part));
+
+        let mut token = [0u8; TOKEN_LEN];
+        for (byte, digits) in mac.finalize().into_bytes().iter().zip(token.chunks_mut(2)) {
+            digits[0] = HEX_DIGITS[(byte >> 4) as usize];
+            digits[1] = HEX_DIGITS[(byte & 0xf) as usize];
+        }
+
+        token
+    }
+}
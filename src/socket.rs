@@ -0,0 +1,108 @@
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::error::AuthError;
+
+/// Which IP versions a client may use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AddressFamily {
+    #[default]
+    Any,
+    Ipv4,
+    Ipv6,
+}
+
+/// How the local end of a client socket is set up.
+///
+/// By default the socket is bound to an ephemeral port on every address,
+/// dual-stack when some server is reached over IPv6 and IPv4-only otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SocketOptions {
+    pub family: AddressFamily,
+    /// Local address and port to send from.
+    pub bind: Option<SocketAddr>,
+    /// Network interface to send through (`SO_BINDTODEVICE`), Linux only.
+    pub interface: Option<String>,
+}
+
+impl SocketOptions {
+    /// Whether a socket set up this way can reach `server`.
+    pub fn allows(&self, server: &SocketAddr) -> bool {
+        let family = match self.family {
+            AddressFamily::Any => true,
+            AddressFamily::Ipv4 => server.is_ipv4(),
+            AddressFamily::Ipv6 => server.is_ipv6(),
+        };
+
+        let local = match self.bind {
+            None => true,
+            Some(SocketAddr::V4(_)) => server.is_ipv4(),
+            Some(SocketAddr::V6(local)) => local.ip().is_unspecified() || server.is_ipv6(),
+        };
+
+        family && local
+    }
+
+    /// Binds a socket that can reach the first of `servers`, and drops the
+    /// servers it cannot reach.
+    ///
+    /// Without a bind address, a host with IPv6 disabled gets an IPv4 socket
+    /// and keeps only the IPv4 servers.
+    pub(crate) fn bind(&self, servers: &mut Vec<SocketAddr>) -> Result<UdpSocket, AuthError> {
+        servers.retain(|server| self.allows(server));
+
+        if servers.is_empty() {
+            return Err(AuthError::InvalidInput(
+                "No server address can be reached with the chosen address family or bind address!".to_string(),
+            ));
+        }
+
+        let ipv4_only = servers.iter().all(SocketAddr::is_ipv4);
+        let local = self.bind.unwrap_or(if ipv4_only {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        } else {
+            (Ipv6Addr::UNSPECIFIED, 0).into()
+        });
+
+        match self.open(local) {
+            Err(e) if self.bind.is_none() && !ipv4_only && self.family == AddressFamily::Any => {
+                servers.retain(SocketAddr::is_ipv4);
+                if servers.is_empty() {
+                    return Err(e.into());
+                }
+                Ok(self.open((Ipv4Addr::UNSPECIFIED, 0).into())?)
+            }
+            socket => Ok(socket?),
+        }
+    }
+
+    fn open(&self, local: SocketAddr) -> io::Result<UdpSocket> {
+        let socket = Socket::new(Domain::for_address(local), Type::DGRAM, Some(Protocol::UDP))?;
+
+        if local.is_ipv6() {
+            socket.set_only_v6(self.family == AddressFamily::Ipv6)?;
+        }
+        if let Some(interface) = &self.interface {
+            bind_device(&socket, interface)
+                .map_err(|e| io::Error::new(e.kind(), format!("Cannot bind to interface {interface:?}: {e}")))?;
+        }
+
+        socket.bind(&local.into())?;
+        Ok(socket.into())
+    }
+}
+
+#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+fn bind_device(socket: &Socket, interface: &str) -> io::Result<()> {
+    socket.bind_device(Some(interface.as_bytes()))
+}
+
+#[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+fn bind_device(_: &Socket, _: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Binding to an interface is only supported on Linux!",
+    ))
+}
@@ -0,0 +1,199 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use udp_auth_client::{AuthError, Gas, Sas};
+
+use crate::output::Outcome;
+
+const DATA_DIRECTORY: &str = "udp-auth-client";
+const STORE_FILE: &str = "tokens.json";
+const LOCK_FILE: &str = "tokens.json.lock";
+
+/// Every SAS and GAS this client has obtained, kept as JSON under
+/// `$XDG_DATA_HOME`, or `~/.local/share` when it is not set.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Store {
+    #[serde(skip)]
+    path: PathBuf,
+    pub tokens: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Sas,
+    Gas,
+}
+
+/// A stored token and what is known about it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub kind: Kind,
+    /// The SAS ID, or the member IDs of a GAS joined with `+`.
+    pub id: String,
+    /// Nonce of a SAS.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub nonce: Option<u32>,
+    /// The full SAS or GAS string.
+    pub token: String,
+    /// Server that issued the token, as given on the command line.
+    pub server: String,
+    /// Seconds since the Unix epoch.
+    pub obtained_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub validated_at: Option<u64>,
+    /// Result of the last validation, if the token was ever validated.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub valid: Option<bool>,
+}
+
+impl Entry {
+    /// `valid`, `invalid` or `unvalidated`.
+    pub fn status(&self) -> &'static str {
+        match self.valid {
+            Some(true) => "valid",
+            Some(false) => "invalid",
+            None => "unvalidated",
+        }
+    }
+}
+
+impl Store {
+    /// Opens the store, which is empty until the first token is recorded.
+    pub fn open() -> Result<Self, AuthError> {
+        let path = data_directory()?.join(STORE_FILE);
+
+        let mut store = match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| AuthError::InvalidInput(format!("Corrupt token store {}: {e}", path.display())))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(AuthError::InvalidInput(format!("Cannot read {}: {e}", path.display()))),
+        };
+
+        store.path = path;
+        Ok(store)
+    }
+
+    /// Opens the store, lets `change` modify it and saves it, holding an
+    /// exclusive lock throughout so that concurrent runs do not overwrite
+    /// each other's tokens.
+    pub fn update(change: impl FnOnce(&mut Self)) -> Result<(), AuthError> {
+        let directory = data_directory()?;
+        let path = directory.join(LOCK_FILE);
+        let failed = |e: std::io::Error| AuthError::InvalidInput(format!("Cannot lock {}: {e}", path.display()));
+
+        fs::create_dir_all(&directory).map_err(failed)?;
+        let lock = File::options().create(true).truncate(false).write(true).open(&path).map_err(failed)?;
+        lock.lock().map_err(failed)?;
+
+        let mut store = Self::open()?;
+        change(&mut store);
+        store.save()
+    }
+
+    /// Writes the store to a temporary file first, so that an interrupted
+    /// write never leaves it truncated and readers need no lock.
+    fn save(&self) -> Result<(), AuthError> {
+        let failed = |e: std::io::Error| AuthError::InvalidInput(format!("Cannot write {}: {e}", self.path.display()));
+
+        let text = serde_json::to_string_pretty(self).expect("the store is always serializable");
+        let temporary = self.path.with_extension(format!("json.{}.tmp", std::process::id()));
+
+        fs::write(&temporary, text + "\n").map_err(failed)?;
+        fs::rename(&temporary, &self.path).map_err(failed)
+    }
+
+    fn record_sas(&mut self, server: &str, sas: &Sas) {
+        let token = sas.to_string();
+
+        if !self.tokens.iter().any(|entry| entry.server == server && entry.token == token) {
+            self.tokens.push(Entry {
+                kind: Kind::Sas,
+                id: sas.id_str().into_owned(),
+                nonce: Some(sas.nonce),
+                token,
+                server: server.to_string(),
+                obtained_at: now(),
+                validated_at: None,
+                valid: None,
+            });
+        }
+    }
+
+    /// Records `gas` and every member SAS it covers.
+    fn record_gas(&mut self, server: &str, gas: &Gas) {
+        let token = gas.to_string();
+
+        for member in &gas.members {
+            self.record_sas(server, member);
+        }
+
+        if !self.tokens.iter().any(|entry| entry.server == server && entry.token == token) {
+            self.tokens.push(Entry {
+                kind: Kind::Gas,
+                id: gas.members.iter().map(|sas| sas.id_str()).collect::<Vec<_>>().join("+"),
+                nonce: None,
+                token,
+                server: server.to_string(),
+                obtained_at: now(),
+                validated_at: None,
+                valid: None,
+            });
+        }
+    }
+
+    /// Records the tokens a command obtained or validated.
+    pub fn record(&mut self, server: &str, outcome: &Outcome) {
+        match outcome {
+            Outcome::Sas(sas) => self.record_sas(server, sas),
+            Outcome::Gas(gas) => self.record_gas(server, gas),
+            Outcome::SasStatus { sas, valid } => self.record_validation(server, &sas.to_string(), *valid),
+            Outcome::GasStatus { gas, valid } => self.record_validation(server, &gas.to_string(), *valid),
+        }
+    }
+
+    /// Remembers the result of validating `token`, if it is stored.
+    fn record_validation(&mut self, server: &str, token: &str, valid: bool) {
+        for entry in self.tokens.iter_mut().filter(|entry| entry.server == server && entry.token == token) {
+            entry.validated_at = Some(now());
+            entry.valid = Some(valid);
+        }
+    }
+
+    /// The most recent SAS issued by `server` for `id` and `nonce`.
+    pub fn find_sas(&self, server: &str, id: &str, nonce: u32) -> Option<Sas> {
+        self.tokens
+            .iter()
+            .rev()
+            .filter(|entry| entry.kind == Kind::Sas && entry.server == server)
+            .find(|entry| entry.id == id && entry.nonce == Some(nonce))
+            .and_then(|entry| entry.token.parse().ok())
+    }
+
+    /// Every stored SAS of `id`, and every GAS that `id` is a member of.
+    pub fn find(&self, id: &str) -> Vec<&Entry> {
+        self.tokens
+            .iter()
+            .filter(|entry| entry.id == id || (entry.kind == Kind::Gas && entry.id.split('+').any(|member| member == id)))
+            .collect()
+    }
+}
+
+/// Where the client keeps its data: `$XDG_DATA_HOME/udp-auth-client`.
+pub fn data_directory() -> Result<PathBuf, AuthError> {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))
+        .ok_or_else(|| AuthError::InvalidInput("Cannot locate the token store: neither XDG_DATA_HOME nor HOME is set!".to_string()))?;
+
+    Ok(data_home.join(DATA_DIRECTORY))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
@@ -0,0 +1,187 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use common::{group_gas, spawn_server, tamper, ALICE_SAS, BOB_SAS, LOOPBACK_ADDRESSES, SECRET};
+use tokio::net::UdpSocket;
+use tokio::task::JoinSet;
+use tokio::time;
+use udp_auth_client::server::{Server, TokenIssuer};
+use udp_auth_client::{AsyncClient, AuthError, ErrorCode, Gas, Message, RetryPolicy, Sas};
+
+async fn connect(address: &str) -> AsyncClient {
+    AsyncClient::connect(spawn_server(address)).await.expect("Failed to connect to test server")
+}
+
+#[tokio::test]
+async fn every_operation_matches_the_sync_client() {
+    for address in LOOPBACK_ADDRESSES {
+        let client = connect(address).await;
+
+        let sas = client.request_individual_token("alice", 7).await.unwrap();
+        assert_eq!(sas.to_string(), ALICE_SAS);
+        assert!(client.validate_individual_token(&sas).await.unwrap());
+
+        let tampered: Sas = tamper(ALICE_SAS).parse().unwrap();
+        assert!(!client.validate_individual_token(&tampered).await.unwrap());
+
+        let members: Vec<Sas> = [ALICE_SAS, BOB_SAS].iter().map(|sas| sas.parse().unwrap()).collect();
+        let gas = client.request_group_token(&members).await.unwrap();
+        assert_eq!(gas.to_string(), group_gas());
+        assert!(client.validate_group_token(&gas).await.unwrap());
+
+        let mut forged: Gas = gas.clone();
+        forged.members.pop();
+        assert!(!client.validate_group_token(&forged).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn concurrent_calls_get_their_own_replies() {
+    let client = Arc::new(connect(LOOPBACK_ADDRESSES[0]).await);
+    let mut calls = JoinSet::new();
+
+    for nonce in 0..200 {
+        let client = Arc::clone(&client);
+        calls.spawn(async move { (nonce, client.request_individual_token("alice", nonce).await) });
+    }
+
+    while let Some(call) = calls.join_next().await {
+        let (nonce, sas) = call.unwrap();
+        assert_eq!(sas.unwrap().nonce, nonce);
+    }
+}
+
+#[tokio::test]
+async fn concurrent_errors_reach_the_call_that_caused_them() {
+    let client = Arc::new(connect(LOOPBACK_ADDRESSES[0]).await);
+    let alice: Sas = ALICE_SAS.parse().unwrap();
+    let tampered: Sas = tamper(BOB_SAS).parse().unwrap();
+
+    let empty = {
+        let client = Arc::clone(&client);
+        tokio::spawn(async move { client.request_group_token(&[]).await })
+    };
+    let forged = {
+        let client = Arc::clone(&client);
+        let members = vec![alice.clone(), tampered];
+        tokio::spawn(async move { client.request_group_token(&members).await })
+    };
+    let valid = {
+        let client = Arc::clone(&client);
+        tokio::spawn(async move { client.validate_individual_token(&alice).await })
+    };
+
+    assert!(matches!(empty.await.unwrap(), Err(AuthError::Server(ErrorCode::InvalidParameter))));
+    assert!(matches!(forged.await.unwrap(), Err(AuthError::Server(ErrorCode::InvalidSingleToken))));
+    assert!(valid.await.unwrap().unwrap());
+}
+
+#[tokio::test]
+async fn retransmits_until_attempts_run_out() {
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let policy = RetryPolicy {
+        initial_timeout: Duration::from_millis(20),
+        jitter: 0.0,
+        retries: 2,
+        ..RetryPolicy::default()
+    };
+    let client = AsyncClient::connect(silent.local_addr().unwrap()).await.unwrap().with_retry_policy(policy);
+
+    let result = client.request_individual_token("alice", 7).await;
+    assert!(matches!(result, Err(AuthError::Timeout { attempts: 3 })), "{result:?}");
+
+    let mut buf = [0; 64];
+    for _ in 0..3 {
+        assert_eq!(silent.recv(&mut buf).await.unwrap(), 18);
+    }
+}
+
+#[tokio::test]
+async fn cancelled_calls_do_not_disturb_later_ones() {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let client = AsyncClient::connect(socket.local_addr().unwrap()).await.unwrap();
+
+    let cancelled = time::timeout(Duration::from_millis(50), client.request_individual_token("bob", 8)).await;
+    assert!(cancelled.is_err());
+
+    let socket = socket.into_std().unwrap();
+    let server = Server::new(socket.try_clone().unwrap(), TokenIssuer::new(SECRET));
+    let socket = UdpSocket::from_std(socket).unwrap();
+
+    tokio::spawn(async move {
+        let mut buf = vec![0; 1024];
+        loop {
+            let (received, peer) = socket.recv_from(&mut buf).await.unwrap();
+            let reply = server.handle(&buf[..received]);
+            socket.send_to(&reply.encode(), peer).await.unwrap();
+        }
+    });
+
+    let sas = client.request_individual_token("alice", 7).await.unwrap();
+    assert_eq!(sas.to_string(), ALICE_SAS);
+}
+
+fn quick_policy() -> RetryPolicy {
+    RetryPolicy {
+        initial_timeout: Duration::from_millis(50),
+        retries: 1,
+        jitter: 0.0,
+        ..RetryPolicy::default()
+    }
+}
+
+#[tokio::test]
+async fn fails_over_to_the_next_server_and_stays_there() {
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let servers = [silent.local_addr().unwrap(), spawn_server(LOOPBACK_ADDRESSES[0])];
+    let client = AsyncClient::connect_all(servers).await.unwrap().with_retry_policy(quick_policy());
+
+    assert_eq!(client.servers(), servers);
+
+    let exchange = client
+        .transact(&Message::IndividualTokenRequest {
+            id: *b"alice\0\0\0\0\0\0\0",
+            nonce: 7,
+        })
+        .await
+        .unwrap();
+    assert_eq!(exchange.server, servers[1]);
+    assert_eq!(exchange.attempts, 1);
+
+    let started = Instant::now();
+    assert_eq!(client.request_individual_token("alice", 7).await.unwrap().to_string(), ALICE_SAS);
+    assert!(started.elapsed() < Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn concurrent_calls_fail_over_together() {
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let servers = [silent.local_addr().unwrap(), spawn_server(LOOPBACK_ADDRESSES[0])];
+    let client = Arc::new(AsyncClient::connect_all(servers).await.unwrap().with_retry_policy(quick_policy()));
+    let mut calls = JoinSet::new();
+
+    for nonce in 0..20 {
+        let client = Arc::clone(&client);
+        calls.spawn(async move { (nonce, client.request_individual_token("alice", nonce).await) });
+    }
+
+    while let Some(call) = calls.join_next().await {
+        let (nonce, sas) = call.unwrap();
+        assert_eq!(sas.unwrap().nonce, nonce);
+    }
+}
+
+#[tokio::test]
+async fn timeouts_count_the_attempts_made_on_every_server() {
+    let silent = [UdpSocket::bind("127.0.0.1:0").await.unwrap(), UdpSocket::bind("127.0.0.1:0").await.unwrap()];
+    let servers: Vec<SocketAddr> = silent.iter().map(|socket| socket.local_addr().unwrap()).collect();
+    let client = AsyncClient::connect_all(servers).await.unwrap().with_retry_policy(quick_policy());
+
+    let error = client.request_individual_token("alice", 7).await.unwrap_err();
+    assert!(matches!(error, AuthError::Timeout { attempts: 4 }), "{error:?}");
+}
@@ -0,0 +1,685 @@
+mod common;
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::net::{SocketAddr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+
+use common::{group_gas, spawn_server, tamper, ALICE_SAS, BOB_SAS, LOOPBACK_ADDRESSES};
+use serde_json::Value;
+use udp_auth_client::{ErrorCode, Message};
+
+const EXIT_INVALID_INPUT: i32 = 1;
+const EXIT_SERVER_ERROR: i32 = 5;
+
+/// The client binary, keeping its token store and config out of the user's
+/// directories and ignoring any settings in the environment.
+fn client(data_home: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_udp-auth-client"));
+    command.env("XDG_DATA_HOME", data_home).env("XDG_CONFIG_HOME", data_home);
+
+    for (name, _) in env::vars_os() {
+        if name.to_string_lossy().starts_with("UDP_AUTH_") {
+            command.env_remove(name);
+        }
+    }
+    command
+}
+
+fn shared_data_home() -> PathBuf {
+    env::temp_dir().join(format!("udp-auth-{}-data", std::process::id()))
+}
+
+fn run_client(server: SocketAddr, args: &[&str]) -> Output {
+    run_client_in(&shared_data_home(), server, args)
+}
+
+fn run_client_in(data_home: &Path, server: SocketAddr, args: &[&str]) -> Output {
+    client(data_home)
+        .arg(server.ip().to_string())
+        .arg(server.port().to_string())
+        .args(args)
+        .output()
+        .expect("Failed to run the client binary")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn itr_prints_the_sas() {
+    for address in LOOPBACK_ADDRESSES {
+        let output = run_client(spawn_server(address), &["itr", "alice", "7"]);
+
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), format!("{ALICE_SAS}\n"));
+    }
+}
+
+#[test]
+fn itv_prints_the_status() {
+    for address in LOOPBACK_ADDRESSES {
+        let server = spawn_server(address);
+
+        let output = run_client(server, &["itv", ALICE_SAS]);
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), "0\n");
+
+        let output = run_client(server, &["itv", &tamper(ALICE_SAS)]);
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), "1\n");
+    }
+}
+
+#[test]
+fn gtr_prints_the_gas() {
+    for address in LOOPBACK_ADDRESSES {
+        let output = run_client(spawn_server(address), &["gtr", "2", ALICE_SAS, BOB_SAS]);
+
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), format!("{}\n", group_gas()));
+    }
+}
+
+#[test]
+fn gtv_prints_the_status() {
+    for address in LOOPBACK_ADDRESSES {
+        let server = spawn_server(address);
+
+        let output = run_client(server, &["gtv", &group_gas()]);
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), "0\n");
+
+        let output = run_client(server, &["gtv", &group_gas().replacen("alice:7", "alice:8", 1)]);
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), "1\n");
+    }
+}
+
+#[test]
+fn server_errors_exit_with_their_message() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+
+    let output = run_client(server, &["gtr", "0"]);
+    assert_eq!(output.status.code(), Some(EXIT_SERVER_ERROR));
+    assert_eq!(stderr(&output), "Error: Invalid parameter!\n");
+
+    let output = run_client(server, &["gtr", "2", ALICE_SAS, &tamper(BOB_SAS)]);
+    assert_eq!(output.status.code(), Some(EXIT_SERVER_ERROR));
+    assert_eq!(stderr(&output), "Error: Invalid single token!\n");
+}
+
+#[test]
+fn unknown_server_error_codes_exit_as_server_errors() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server = socket.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut buf = vec![0; 1024];
+        let (_, peer) = socket.recv_from(&mut buf).unwrap();
+        let reply = Message::ErrorMessage(ErrorCode::Unknown(42));
+
+        socket.send_to(&reply.encode(), peer).unwrap();
+    });
+
+    let output = run_client(server, &["itr", "alice", "7"]);
+    assert_eq!(output.status.code(), Some(EXIT_SERVER_ERROR));
+    assert_eq!(stderr(&output), "Error: Unknown error code 42!\n");
+}
+
+#[test]
+fn invalid_input_is_rejected_before_sending() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+
+    for args in [&["itr", "alice", "x"][..], &["itv", "alice:7"], &["gtr", "2", ALICE_SAS], &["xyz"]] {
+        let output = run_client(server, args);
+
+        assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT), "{args:?}");
+        assert!(stdout(&output).is_empty());
+    }
+}
+
+#[test]
+fn help_lists_the_commands_and_succeeds() {
+    let output = client(&shared_data_home()).arg("--help").output().unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    for command in ["itr", "itv", "gtr", "gtv", "group-enroll", "roster", "batch", "interactive", "tokens"] {
+        assert!(stdout(&output).contains(command), "{command}");
+    }
+}
+
+#[test]
+fn usage_errors_name_the_offending_argument() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+
+    let output = run_client(server, &["itr", "alice"]);
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+    assert!(stderr(&output).contains("<NONCE>"));
+
+    let output = run_client(server, &["gtr", "2", ALICE_SAS]);
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+    assert_eq!(stderr(&output), "Expected 2 SAS values, but received 1\n");
+
+    let output = run_client(server, &["itr", "a:b", "1"]);
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+    assert!(stderr(&output).contains("ID must not contain ':'"));
+
+    let output = run_client(server, &["--timeout", "1e19", "batch", "-"]);
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+    assert!(stderr(&output).contains("not a number of seconds up to 86400"));
+
+    let output = client(&shared_data_home()).args(["itr", "alice", "7"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+    assert!(stderr(&output).starts_with("No server given!"));
+}
+
+#[test]
+fn the_server_can_be_given_as_options() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let output = client(&shared_data_home())
+        .args(["itr", "alice", "7", "--host", &server.ip().to_string(), "--port", &server.port().to_string()])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output).trim_end(), ALICE_SAS);
+}
+
+/// Writes a config file defining a `local` profile for `server` under `home`.
+fn write_config(home: &Path, server: SocketAddr, extra: &str) {
+    let directory = home.join("udp-auth-client");
+    let config = format!(
+        "[profile.local]\nhost = \"{}\"\nport = {}\n{extra}\n[profile.broken]\nhost = \"127.0.0.1\"\nport = 9\n",
+        server.ip(),
+        server.port()
+    );
+
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("config.toml"), config).unwrap();
+}
+
+#[test]
+fn profiles_supply_the_server_and_defaults() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let home = private_data_home("profiles");
+    write_config(&home, server, "output = \"json\"");
+
+    let output = client(&home).args(["--profile", "local", "itr", "alice", "7"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(json_stdout(&output)["sas"], ALICE_SAS);
+
+    let output = client(&home).env("UDP_AUTH_PROFILE", "local").args(["--output", "text", "itr", "alice", "7"]).output().unwrap();
+    assert_eq!(stdout(&output).trim_end(), ALICE_SAS);
+
+    let output = client(&home).args(["--profile", "staging", "itr", "alice", "7"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+    assert!(stderr(&output).contains("Unknown profile \"staging\"! Profiles in"));
+    assert!(stderr(&output).contains("broken, local"));
+
+    fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn environment_overrides_the_profile() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let home = private_data_home("environment");
+    write_config(&home, server, "");
+
+    let output = client(&home)
+        .env("UDP_AUTH_PROFILE", "broken")
+        .env("UDP_AUTH_HOST", server.ip().to_string())
+        .env("UDP_AUTH_PORT", server.port().to_string())
+        .args(["itr", "alice", "7"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output).trim_end(), ALICE_SAS);
+
+    fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn failover_reports_the_server_that_answered() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let servers = [silent.local_addr().unwrap().to_string(), server.to_string()];
+    let args = ["--timeout", "0.05", "--retries", "0", "--server", &servers[0], "--server", &servers[1]];
+
+    let output = client(&shared_data_home()).args(args).args(["itr", "alice", "7"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output).trim_end(), ALICE_SAS);
+    assert_eq!(stderr(&output), format!("Answered by {server} after failover.\n"));
+
+    let output = client(&shared_data_home()).args(args).args(["--output", "json", "itr", "alice", "7"]).output().unwrap();
+    assert_eq!(json_stdout(&output)["server"], server.to_string());
+}
+
+#[test]
+fn address_family_options_restrict_the_servers() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[1]);
+
+    let output = run_client(server, &["-6", "itr", "alice", "7"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = run_client(server, &["-4", "itr", "alice", "7"]);
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+
+    let output = run_client(server, &["-4", "-6", "itr", "alice", "7"]);
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+}
+
+fn json_stdout(output: &Output) -> Value {
+    serde_json::from_slice(&output.stdout).expect("stdout is not a JSON document")
+}
+
+#[test]
+fn json_output_describes_each_command() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+
+    let itr = json_stdout(&run_client(server, &["--output", "json", "itr", "alice", "7"]));
+    assert_eq!(itr["command"], "itr");
+    assert_eq!(itr["id"], "alice");
+    assert_eq!(itr["nonce"], 7);
+    assert_eq!(itr["sas"], ALICE_SAS);
+    assert_eq!(itr["attempts"], 1);
+    assert!(itr["rtt_ms"].as_f64().unwrap() >= 0.0);
+
+    let itv = json_stdout(&run_client(server, &["--output", "json", "itv", &tamper(ALICE_SAS)]));
+    assert_eq!(itv["command"], "itv");
+    assert_eq!(itv["id"], "alice");
+    assert_eq!(itv["valid"], false);
+
+    let gtr = json_stdout(&run_client(server, &["--output", "json", "gtr", "2", ALICE_SAS, BOB_SAS]));
+    assert_eq!(gtr["gas"], group_gas());
+    assert_eq!(gtr["members"][1]["id"], "bob");
+
+    let gtv = json_stdout(&run_client(server, &["--output", "json", "gtv", &group_gas()]));
+    assert_eq!(gtv["valid"], true);
+    assert_eq!(gtv["members"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn json_output_reports_errors_as_objects() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+
+    let output = run_client(server, &["--output", "json", "gtr", "0"]);
+    assert_eq!(output.status.code(), Some(EXIT_SERVER_ERROR));
+
+    let error = json_stdout(&output);
+    assert_eq!(error["command"], "gtr");
+    assert_eq!(error["error"]["kind"], "server");
+    assert_eq!(error["error"]["code"], 3);
+    assert_eq!(error["error"]["exit_code"], EXIT_SERVER_ERROR);
+
+    let output = run_client(server, &["--output", "json", "itr", "alice", "x"]);
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+    assert_eq!(json_stdout(&output)["error"]["kind"], "invalid_input");
+}
+
+#[test]
+fn unknown_output_formats_are_rejected() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let output = run_client(server, &["--output", "yaml", "itr", "alice", "7"]);
+
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+    assert!(stdout(&output).is_empty());
+}
+
+fn run_with_stdin(server: SocketAddr, args: &[&str], input: &str) -> Output {
+    let mut child = client(&shared_data_home())
+        .arg(server.ip().to_string())
+        .arg(server.port().to_string())
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run the client binary");
+
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn batch_prints_one_line_per_command_in_order() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let input = format!(
+        "# roster\nitr alice 7\n\nitv {}\nitr bob 8\ngtr 2 {ALICE_SAS} {BOB_SAS}\ngtv {}\n",
+        tamper(ALICE_SAS),
+        group_gas()
+    );
+
+    let output = run_with_stdin(server, &["batch", "-"], &input);
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), format!("{ALICE_SAS}\n1\n{BOB_SAS}\n{}\n0\n", group_gas()));
+}
+
+#[test]
+fn batch_reports_failures_per_line_and_keeps_going() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let output = run_with_stdin(server, &["batch", "-"], "itr alice x\ngtr 0\nitr alice 7\n");
+
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+    let stdout = stdout(&output);
+    let lines: Vec<&str> = stdout.lines().collect();
+
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("line 1: invalid value 'x' for '<NONCE>'"));
+    assert_eq!(lines[1], "line 2: Error: Invalid parameter!");
+    assert_eq!(lines[2], ALICE_SAS);
+}
+
+#[test]
+fn batch_reads_files_and_prints_json_lines() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let path = env::temp_dir().join(format!("udp-auth-batch-{}.txt", std::process::id()));
+    fs::write(&path, "itr alice 7\ngtr 0\n").unwrap();
+
+    let output = run_client(server, &["--output", "json", "batch", path.to_str().unwrap()]);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(EXIT_SERVER_ERROR));
+    let lines: Vec<Value> = stdout(&output).lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+
+    assert_eq!(lines[0]["line"], 1);
+    assert_eq!(lines[0]["sas"], ALICE_SAS);
+    assert_eq!(lines[1]["line"], 2);
+    assert_eq!(lines[1]["command"], "gtr");
+    assert_eq!(lines[1]["error"]["kind"], "server");
+}
+
+#[test]
+fn batch_rejects_missing_files() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let output = run_client(server, &["batch", "/nonexistent/udp-auth-batch.txt"]);
+
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+    assert!(stdout(&output).is_empty());
+}
+
+#[test]
+fn group_enroll_prints_the_gas_of_all_members() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+
+    let output = run_client(server, &["group-enroll", "--member", "alice:7", "--member", "bob:8"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), format!("{}\n", group_gas()));
+
+    let output = run_with_stdin(server, &["group-enroll", "--members-file", "-"], "# class\nalice:7\n\nbob:8\n");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), format!("{}\n", group_gas()));
+}
+
+#[test]
+fn group_enroll_fails_without_a_gas_if_a_member_fails() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let args = ["group-enroll", "--member", "alice:7", "--member", "an-id-that-is-too-long:1"];
+
+    let output = run_client(server, &args);
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+    assert!(stdout(&output).is_empty());
+    assert!(stderr(&output).starts_with("Member an-id-that-is-too-long:1: ID is too long!"));
+
+    let output = run_client(server, &[&["--output", "json"][..], &args].concat());
+    let error = json_stdout(&output);
+    assert_eq!(error["command"], "group-enroll");
+    assert_eq!(error["error"]["member"], "an-id-that-is-too-long:1");
+    assert_eq!(error["error"]["kind"], "invalid_input");
+
+    let output = run_client(server, &["group-enroll"]);
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+}
+
+/// Writes `contents` to a file in the temporary directory, unique to this test.
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("udp-auth-{}-{name}", std::process::id()));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+const TOML_ROSTER: &str = r#"
+[[group]]
+name = "team-1"
+members = [{ id = "alice", nonce = 7 }, { id = "bob", nonce = 8 }]
+
+[[group]]
+name = "team-2"
+members = [{ id = "an-id-that-is-too-long", nonce = 1 }]
+"#;
+
+#[test]
+fn roster_writes_every_sas_and_gas_as_csv() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let roster = temp_file("roster.csv", "group,id,nonce\nteam-1,alice,7\nteam-1,bob,8\n");
+
+    let output = run_client(server, &["roster", roster.to_str().unwrap()]);
+    fs::remove_file(&roster).unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        format!(
+            "group,id,nonce,sas,gas,error\nteam-1,alice,7,{ALICE_SAS},{gas},\nteam-1,bob,8,{BOB_SAS},{gas},\n",
+            gas = group_gas()
+        )
+    );
+}
+
+#[test]
+fn roster_writes_json_files_and_reports_failed_groups() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let roster = temp_file("roster.toml", TOML_ROSTER);
+    let results = env::temp_dir().join(format!("udp-auth-{}-results.json", std::process::id()));
+
+    let output = run_client(server, &["roster", roster.to_str().unwrap(), "--out", results.to_str().unwrap()]);
+    let document: Value = serde_json::from_str(&fs::read_to_string(&results).unwrap()).unwrap();
+    fs::remove_file(&roster).unwrap();
+    fs::remove_file(&results).unwrap();
+
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+    assert!(stdout(&output).is_empty());
+
+    let groups = document["groups"].as_array().unwrap();
+    assert_eq!(groups[0]["name"], "team-1");
+    assert_eq!(groups[0]["members"][0]["sas"], ALICE_SAS);
+    assert_eq!(groups[0]["gas"], group_gas());
+    assert_eq!(groups[1]["name"], "team-2");
+    assert!(groups[1]["members"][0]["error"].as_str().unwrap().starts_with("ID is too long!"));
+    assert!(groups[1]["gas"].is_null());
+    assert!(groups[1]["error"].is_string());
+}
+
+#[test]
+fn roster_rejects_unknown_formats() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let roster = temp_file("roster.txt", "alice:7\n");
+
+    let output = run_client(server, &["roster", roster.to_str().unwrap()]);
+    fs::remove_file(&roster).unwrap();
+
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+}
+
+#[test]
+fn roster_rejects_groups_without_members() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let roster = temp_file(
+        "empty-group.toml",
+        "[[group]]\nname = \"team-1\"\nmembers = [{ id = \"alice\", nonce = 7 }]\n\n[[group]]\nname = \"team-2\"\n",
+    );
+
+    let output = run_client(server, &["roster", roster.to_str().unwrap()]);
+    fs::remove_file(&roster).unwrap();
+
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+    assert!(stderr(&output).ends_with("group \"team-2\" has no members.\n"), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "");
+}
+
+/// A fresh data directory for tests that inspect the token store.
+fn private_data_home(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("udp-auth-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    path
+}
+
+fn run_tokens(data_home: &Path, args: &[&str]) -> Output {
+    client(data_home).arg("tokens").args(args).output().expect("Failed to run the client binary")
+}
+
+#[test]
+fn concurrent_runs_keep_every_token() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let home = private_data_home("concurrent");
+
+    let children: Vec<_> = (0..30)
+        .map(|nonce| {
+            client(&home)
+                .args([server.ip().to_string(), server.port().to_string()])
+                .args(["itr", &format!("user{nonce}"), &nonce.to_string()])
+                .stdout(Stdio::null())
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+
+    for mut child in children {
+        assert!(child.wait().unwrap().success());
+    }
+
+    let output = run_tokens(&home, &["--output", "json", "list"]);
+    let entries: Vec<Value> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(entries.len(), 30);
+
+    fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn obtained_tokens_are_stored_with_their_validation() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let home = private_data_home("store");
+
+    run_client_in(&home, server, &["gtr", "2", ALICE_SAS, BOB_SAS]);
+    run_client_in(&home, server, &["itv", ALICE_SAS]);
+
+    let output = run_tokens(&home, &["--output", "json", "list"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let entries = json_stdout(&output);
+    let entries = entries.as_array().unwrap();
+
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0]["kind"], "sas");
+    assert_eq!(entries[0]["token"], ALICE_SAS);
+    assert_eq!(entries[0]["server"], format!("{}:{}", server.ip(), server.port()));
+    assert_eq!(entries[0]["valid"], true);
+    assert_eq!(entries[2]["kind"], "gas");
+    assert_eq!(entries[2]["id"], "alice+bob");
+
+    let shown = stdout(&run_tokens(&home, &["show", "bob"]));
+    assert_eq!(shown.lines().count(), 2);
+    assert!(shown.lines().all(|line| line.contains(BOB_SAS)));
+
+    let output = run_tokens(&home, &["show", "carol"]);
+    assert_eq!(output.status.code(), Some(EXIT_INVALID_INPUT));
+
+    let export: Value = serde_json::from_slice(&run_tokens(&home, &["export"]).stdout).unwrap();
+    assert_eq!(export["tokens"].as_array().unwrap().len(), 3);
+
+    fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn use_cache_answers_itr_without_the_server() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let home = private_data_home("cache");
+
+    assert!(run_client_in(&home, server, &["itr", "alice", "7"]).status.success());
+
+    // File the token under a host that cannot be resolved, so that answering
+    // from the store must not touch DNS or the network.
+    let store = home.join("udp-auth-client").join("tokens.json");
+    let tokens = fs::read_to_string(&store).unwrap();
+    let offline = format!("nonexistent.invalid:{}", server.port());
+    fs::write(&store, tokens.replace(&server.to_string(), &offline)).unwrap();
+
+    let output = client(&home)
+        .args(["nonexistent.invalid", &server.port().to_string()])
+        .args(["--use-cache", "--output", "json", "itr", "alice", "7"])
+        .output()
+        .unwrap();
+    let report = json_stdout(&output);
+    assert_eq!(report["sas"], ALICE_SAS);
+    assert_eq!(report["cached"], true);
+    assert!(report.get("attempts").is_none());
+
+    let output = run_client_in(&home, server, &["--use-cache", "--output", "json", "itr", "alice", "8"]);
+    let report = json_stdout(&output);
+    assert_eq!(report["nonce"], 8);
+    assert_eq!(report["attempts"], 1);
+
+    fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn interactive_sessions_reuse_earlier_results() {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let input = "itr alice 7\nitr bob 8\ngtr 2 $alice $2\ngtv $3\n$9\nlast\nquit\nitr carol 1\n";
+
+    let output = run_with_stdin(server, &["interactive"], input);
+    assert!(output.status.success());
+
+    let stdout = stdout(&output);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], format!("$1 = {ALICE_SAS}"));
+    assert_eq!(lines[1], format!("$2 = {BOB_SAS}"));
+    assert_eq!(lines[2], format!("$3 = {}", group_gas()));
+    assert_eq!(lines[3], "0");
+    assert!(lines[4].starts_with("request  GroupTokenValidation (228 bytes): 00 07 00 02 61 6c"));
+    assert!(lines[5].starts_with("reply    GroupTokenStatus (229 bytes): 00 08"));
+    assert_eq!(lines[6], "attempts 1");
+    assert_eq!(lines[8], format!("server   {server}"));
+    assert!(!stdout.contains("carol"));
+
+    assert_eq!(stderr(&output), "Unknown variable $9!\n");
+}
+
+#[test]
+fn interactive_last_shows_the_datagrams_of_failed_requests() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server = socket.local_addr().unwrap();
+
+    // Ignores the first request and answers the second with a datagram too
+    // short to decode.
+    thread::spawn(move || {
+        let mut buf = [0; 1024];
+        socket.recv_from(&mut buf).unwrap();
+        let (_, peer) = socket.recv_from(&mut buf).unwrap();
+        socket.send_to(&[0, 2, 1], peer).unwrap();
+    });
+
+    let input = "itr alice 7\nlast\nitr bob 8\nlast\n";
+    let output = run_with_stdin(server, &["--timeout", "0.2", "--retries", "0", "interactive"], input);
+    assert!(output.status.success());
+
+    let stdout = stdout(&output);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "request  IndividualTokenRequest (18 bytes): 00 01 61 6c 69 63 65 00 00 00 00 00 00 00 00 00 00 07");
+    assert_eq!(lines[1], "reply    none");
+    assert_eq!(lines[2], "error    No response from server after 1 attempts!");
+    assert_eq!(lines[3], format!("server   {server}"));
+    assert!(lines[4].starts_with("request  IndividualTokenRequest (18 bytes): 00 01 62 6f 62"));
+    assert_eq!(lines[5], "reply    malformed (3 bytes): 00 02 01");
+    assert!(lines[6].starts_with("error    Malformed datagram"), "{}", lines[6]);
+    assert_eq!(lines[7], format!("server   {server}"));
+}
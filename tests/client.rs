@@ -0,0 +1,494 @@
+mod common;
+
+use std::collections::HashSet;
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::{group_gas, spawn_server, tamper, ALICE_SAS, BOB_SAS, LOOPBACK_ADDRESSES, SECRET};
+use udp_auth_client::server::{Server, TokenIssuer};
+use udp_auth_client::authentication::package::MAX_MEMBERS;
+use udp_auth_client::{
+    AddressFamily, AuthError, Client, DecodeError, ErrorCode, Gas, Message, RetryPolicy, Sas, SocketOptions,
+};
+
+fn connect(address: &str) -> Client {
+    Client::connect(spawn_server(address)).expect("Failed to connect to test server")
+}
+
+#[test]
+fn itr_returns_the_keyed_sas() {
+    for address in LOOPBACK_ADDRESSES {
+        let client = connect(address);
+        let sas = client.request_individual_token("alice", 7).unwrap();
+
+        assert_eq!(sas.to_string(), ALICE_SAS);
+    }
+}
+
+#[test]
+fn itv_accepts_issued_sas_and_rejects_tampered_ones() {
+    for address in LOOPBACK_ADDRESSES {
+        let client = connect(address);
+        let valid: Sas = ALICE_SAS.parse().unwrap();
+        let tampered: Sas = tamper(ALICE_SAS).parse().unwrap();
+
+        assert!(client.validate_individual_token(&valid).unwrap());
+        assert!(!client.validate_individual_token(&tampered).unwrap());
+    }
+}
+
+#[test]
+fn gtr_returns_the_keyed_gas() {
+    for address in LOOPBACK_ADDRESSES {
+        let client = connect(address);
+        let members: Vec<Sas> = [ALICE_SAS, BOB_SAS].iter().map(|sas| sas.parse().unwrap()).collect();
+        let gas = client.request_group_token(&members).unwrap();
+
+        assert_eq!(gas.to_string(), group_gas());
+    }
+}
+
+#[test]
+fn gtv_accepts_issued_gas_and_rejects_tampered_ones() {
+    for address in LOOPBACK_ADDRESSES {
+        let client = connect(address);
+        let valid: Gas = group_gas().parse().unwrap();
+        let tampered: Gas = format!("{ALICE_SAS}+{}+{}", tamper(BOB_SAS), valid.token_str()).parse().unwrap();
+
+        assert!(client.validate_group_token(&valid).unwrap());
+        assert!(!client.validate_group_token(&tampered).unwrap());
+    }
+}
+
+#[test]
+fn gtr_without_members_is_an_invalid_parameter() {
+    let client = connect(LOOPBACK_ADDRESSES[0]);
+
+    match client.request_group_token(&[]) {
+        Err(AuthError::Server(error)) => assert_eq!(error, ErrorCode::InvalidParameter),
+        other => panic!("Expected a server error, got {other:?}"),
+    }
+}
+
+#[test]
+fn gtr_with_a_tampered_member_is_an_invalid_single_token() {
+    let client = connect(LOOPBACK_ADDRESSES[0]);
+    let members: Vec<Sas> = [ALICE_SAS.to_string(), tamper(BOB_SAS)].iter().map(|sas| sas.parse().unwrap()).collect();
+
+    match client.request_group_token(&members) {
+        Err(AuthError::Server(error)) => assert_eq!(error, ErrorCode::InvalidSingleToken),
+        other => panic!("Expected a server error, got {other:?}"),
+    }
+}
+
+/// Answers one request, but only after sending every datagram in `noise`.
+fn spawn_noisy_server(noise: Vec<Message>) -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let local = socket.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut buf = vec![0; 1024];
+        let (received, peer) = socket.recv_from(&mut buf).unwrap();
+
+        for message in &noise {
+            socket.send_to(&message.encode(), peer).unwrap();
+        }
+
+        let reply = Server::new(socket.try_clone().unwrap(), TokenIssuer::new(SECRET)).handle(&buf[..received]);
+        socket.send_to(&reply.encode(), peer).unwrap();
+    });
+
+    local
+}
+
+#[test]
+fn itr_discards_replies_for_other_requests() {
+    let other_nonce: Sas = tamper(ALICE_SAS).parse().unwrap();
+    let other_id: Sas = BOB_SAS.parse().unwrap();
+    let other_type = Message::IndividualTokenRequest {
+        id: other_id.id,
+        nonce: 7,
+    };
+
+    let longer_reply = Message::GroupTokenStatus {
+        gas: group_gas().parse().unwrap(),
+        status: 0,
+    };
+
+    let noise = vec![
+        Message::IndividualTokenResponse(other_nonce),
+        Message::IndividualTokenResponse(other_id),
+        other_type,
+        longer_reply,
+    ];
+
+    let client = Client::connect(spawn_noisy_server(noise)).unwrap();
+    let sas = client.request_individual_token("alice", 7).unwrap();
+
+    assert_eq!(sas.to_string(), ALICE_SAS);
+    assert_eq!(client.discarded_datagrams(), 4);
+}
+
+#[test]
+fn gtv_discards_status_for_another_group() {
+    let gas: Gas = group_gas().parse().unwrap();
+    let mut other = gas.clone();
+    other.members.pop();
+
+    let noise = vec![Message::GroupTokenStatus { gas: other, status: 0 }];
+
+    let client = Client::connect(spawn_noisy_server(noise)).unwrap();
+
+    assert!(client.validate_group_token(&gas).unwrap());
+    assert_eq!(client.discarded_datagrams(), 1);
+}
+
+fn silent_client(policy: RetryPolicy) -> (Client, UdpSocket) {
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let client = Client::connect(silent.local_addr().unwrap()).unwrap().with_retry_policy(policy);
+
+    (client, silent)
+}
+
+#[test]
+fn retransmits_with_backoff_until_attempts_run_out() {
+    let policy = RetryPolicy {
+        initial_timeout: Duration::from_millis(20),
+        multiplier: 2.0,
+        jitter: 0.0,
+        retries: 2,
+        ..RetryPolicy::default()
+    };
+    let (client, silent) = silent_client(policy);
+
+    let started = Instant::now();
+    let result = client.request_individual_token("alice", 7);
+
+    assert!(matches!(result, Err(AuthError::Timeout { attempts: 3 })), "{result:?}");
+    assert!(started.elapsed() >= Duration::from_millis(140));
+
+    let failure = client.last_failure().unwrap();
+    assert_eq!(failure.sent, &b"\x00\x01alice\0\0\0\0\0\0\0\0\0\0\x07"[..]);
+    assert_eq!(failure.received, None);
+    assert_eq!(failure.server, silent.local_addr().unwrap());
+
+    let mut buf = [0; 64];
+    silent.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+    for _ in 0..3 {
+        assert_eq!(silent.recv(&mut buf).unwrap(), 18);
+    }
+}
+
+#[test]
+fn stops_retransmitting_at_the_deadline() {
+    let policy = RetryPolicy {
+        initial_timeout: Duration::from_millis(50),
+        jitter: 0.0,
+        retries: 100,
+        deadline: Some(Duration::from_millis(200)),
+        ..RetryPolicy::default()
+    };
+    let (client, _silent) = silent_client(policy);
+
+    let started = Instant::now();
+    let result = client.request_individual_token("alice", 7);
+
+    assert!(matches!(result, Err(AuthError::Timeout { attempts: 3 })), "{result:?}");
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn transact_returns_the_matching_reply_and_its_attempts() {
+    let client = connect(LOOPBACK_ADDRESSES[0]);
+    let sas: Sas = ALICE_SAS.parse().unwrap();
+
+    let exchange = client.transact(&Message::IndividualTokenValidation(sas.clone())).unwrap();
+
+    assert_eq!(exchange.sent, Message::IndividualTokenValidation(sas.clone()).encode());
+    assert_eq!(exchange.received, exchange.reply.encode());
+    assert_eq!(exchange.reply, Message::IndividualTokenStatus { sas, status: 0 });
+    assert_eq!(exchange.attempts, 1);
+    assert_eq!(exchange.discarded, 0);
+    assert!(client.last_failure().is_none());
+}
+
+/// Answers the first request with `reply` cut or padded to `len` bytes.
+fn spawn_resizing_server(reply: Message, len: usize) -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let local = socket.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut buf = vec![0; 1024];
+        let (_, peer) = socket.recv_from(&mut buf).unwrap();
+
+        let mut datagram = reply.encode();
+        datagram.resize(len, b'0');
+        socket.send_to(&datagram, peer).unwrap();
+    });
+
+    local
+}
+
+#[test]
+fn short_reply_is_reported_instead_of_panicking() {
+    let reply = Message::IndividualTokenResponse(ALICE_SAS.parse().unwrap());
+    let client = Client::connect(spawn_resizing_server(reply, 40)).unwrap();
+
+    match client.request_individual_token("alice", 7) {
+        Err(AuthError::MalformedDatagram(error)) => {
+            assert_eq!(error, DecodeError::IncorrectMessageLength { expected: 82, found: 40 })
+        }
+        other => panic!("Expected a malformed datagram, got {other:?}"),
+    }
+
+    let received = client.last_failure().unwrap().received.unwrap();
+    assert_eq!(received.len(), 40);
+    assert_eq!(received[..2], [0, 2]);
+}
+
+#[test]
+fn oversized_reply_is_rejected_instead_of_truncated() {
+    let gas: Gas = group_gas().parse().unwrap();
+    let reply = Message::GroupTokenStatus { gas: gas.clone(), status: 0 };
+    let client = Client::connect(spawn_resizing_server(reply, 229 + 80)).unwrap();
+
+    match client.validate_group_token(&gas) {
+        Err(AuthError::MalformedDatagram(error)) => {
+            assert_eq!(error, DecodeError::IncorrectMessageLength { expected: 229, found: 309 })
+        }
+        other => panic!("Expected a malformed datagram, got {other:?}"),
+    }
+}
+
+#[test]
+fn transact_all_returns_results_in_request_order() {
+    let client = connect(LOOPBACK_ADDRESSES[0]);
+    let requests: Vec<(String, u32)> = (0..1000).map(|n| (format!("student{n}"), n)).collect();
+    let requests: Vec<(&str, u32)> = requests.iter().map(|(id, nonce)| (id.as_str(), *nonce)).collect();
+
+    let tokens = client.request_individual_tokens(&requests, 64);
+
+    assert_eq!(tokens.len(), requests.len());
+    for ((id, nonce), sas) in requests.iter().zip(tokens) {
+        let sas = sas.unwrap();
+        assert_eq!((sas.id_str().as_ref(), sas.nonce), (*id, *nonce));
+    }
+
+    let alice = client.request_individual_tokens(&[("alice", 7), ("an-id-that-is-too-long", 1)], 8);
+    assert_eq!(alice[0].as_ref().unwrap().to_string(), ALICE_SAS);
+    assert!(matches!(alice[1], Err(AuthError::InvalidToken(_))), "{:?}", alice[1]);
+}
+
+#[test]
+fn transact_all_pins_error_messages_on_the_request_that_caused_them() {
+    let client = connect(LOOPBACK_ADDRESSES[0]);
+    let alice: Sas = ALICE_SAS.parse().unwrap();
+    let tampered: Sas = tamper(BOB_SAS).parse().unwrap();
+
+    let requests = vec![
+        Message::GroupTokenRequest(vec![]),
+        Message::IndividualTokenValidation(alice.clone()),
+        Message::GroupTokenRequest(vec![alice.clone(), tampered]),
+        Message::IndividualTokenRequest { id: alice.id, nonce: 7 },
+    ];
+
+    let replies: Vec<Message> = client
+        .transact_all(&requests, 4)
+        .into_iter()
+        .map(|exchange| exchange.unwrap().reply)
+        .collect();
+
+    assert_eq!(
+        replies,
+        vec![
+            Message::ErrorMessage(ErrorCode::InvalidParameter),
+            Message::IndividualTokenStatus { sas: alice.clone(), status: 0 },
+            Message::ErrorMessage(ErrorCode::InvalidSingleToken),
+            Message::IndividualTokenResponse(alice),
+        ]
+    );
+}
+
+/// Answers every request, but only the second time it receives it.
+fn spawn_lossy_server() -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let local = socket.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let server = Server::new(socket.try_clone().unwrap(), TokenIssuer::new(SECRET));
+        let mut seen = HashSet::new();
+        let mut buf = vec![0; 1024];
+
+        loop {
+            let (received, peer) = socket.recv_from(&mut buf).unwrap();
+
+            if seen.insert(buf[..received].to_vec()) {
+                continue;
+            }
+
+            let reply = server.handle(&buf[..received]);
+            socket.send_to(&reply.encode(), peer).unwrap();
+        }
+    });
+
+    local
+}
+
+#[test]
+fn transact_all_retransmits_each_request_on_its_own() {
+    let policy = RetryPolicy {
+        initial_timeout: Duration::from_millis(50),
+        jitter: 0.0,
+        ..RetryPolicy::default()
+    };
+    let client = Client::connect(spawn_lossy_server()).unwrap().with_retry_policy(policy);
+
+    let requests: Vec<Message> = (0..20)
+        .map(|nonce| Message::IndividualTokenRequest {
+            id: *b"alice\0\0\0\0\0\0\0",
+            nonce,
+        })
+        .collect();
+
+    let started = Instant::now();
+    let exchanges = client.transact_all(&requests, 20);
+
+    assert!(started.elapsed() < Duration::from_secs(1));
+    for (request, exchange) in requests.iter().zip(exchanges) {
+        let exchange = exchange.unwrap();
+
+        assert!(exchange.reply.is_reply_to(request));
+        assert_eq!(exchange.attempts, 2);
+    }
+}
+
+fn quick_policy() -> RetryPolicy {
+    RetryPolicy {
+        initial_timeout: Duration::from_millis(50),
+        retries: 1,
+        jitter: 0.0,
+        ..RetryPolicy::default()
+    }
+}
+
+#[test]
+fn fails_over_to_the_next_server_and_stays_there() {
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let servers = [silent.local_addr().unwrap(), spawn_server(LOOPBACK_ADDRESSES[0])];
+    let client = Client::connect_all(servers).unwrap().with_retry_policy(quick_policy());
+
+    assert_eq!(client.servers(), servers);
+
+    let exchange = client
+        .transact(&Message::IndividualTokenRequest {
+            id: *b"alice\0\0\0\0\0\0\0",
+            nonce: 7,
+        })
+        .unwrap();
+    assert_eq!(exchange.server, servers[1]);
+    assert_eq!(exchange.attempts, 1);
+
+    let started = Instant::now();
+    assert_eq!(client.request_individual_token("alice", 7).unwrap().to_string(), ALICE_SAS);
+    assert!(started.elapsed() < Duration::from_millis(50));
+}
+
+#[test]
+fn transact_all_fails_over_the_unanswered_requests() {
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let servers = [silent.local_addr().unwrap(), spawn_server(LOOPBACK_ADDRESSES[0])];
+    let client = Client::connect_all(servers).unwrap().with_retry_policy(quick_policy());
+
+    let tokens = client.request_individual_tokens(&[("alice", 7), ("bob", 8)], 2);
+
+    assert_eq!(tokens[0].as_ref().unwrap().to_string(), ALICE_SAS);
+    assert_eq!(tokens[1].as_ref().unwrap().to_string(), BOB_SAS);
+}
+
+#[test]
+fn timeouts_count_the_attempts_made_on_every_server() {
+    let silent = [UdpSocket::bind("127.0.0.1:0").unwrap(), UdpSocket::bind("127.0.0.1:0").unwrap()];
+    let servers: Vec<SocketAddr> = silent.iter().map(|socket| socket.local_addr().unwrap()).collect();
+    let client = Client::connect_all(servers).unwrap().with_retry_policy(quick_policy());
+
+    let error = client.request_individual_token("alice", 7).unwrap_err();
+    assert!(matches!(error, AuthError::Timeout { attempts: 4 }), "{error:?}");
+}
+
+#[test]
+fn address_family_leaves_out_the_other_servers() {
+    let servers = [spawn_server(LOOPBACK_ADDRESSES[1]), spawn_server(LOOPBACK_ADDRESSES[0])];
+    let ipv4 = SocketOptions {
+        family: AddressFamily::Ipv4,
+        ..SocketOptions::default()
+    };
+
+    let client = Client::connect_with(servers, &ipv4).unwrap();
+    assert_eq!(client.servers(), &servers[1..]);
+    assert!(client.socket().local_addr().unwrap().is_ipv4());
+    assert_eq!(client.request_individual_token("alice", 7).unwrap().to_string(), ALICE_SAS);
+
+    let ipv6 = SocketOptions {
+        family: AddressFamily::Ipv6,
+        ..SocketOptions::default()
+    };
+    let error = Client::connect_with(&servers[1..], &ipv6).err().unwrap();
+    assert!(matches!(error, AuthError::InvalidInput(_)), "{error:?}");
+}
+
+#[test]
+fn sends_from_the_requested_local_address() {
+    let free = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let options = SocketOptions {
+        bind: Some(free),
+        ..SocketOptions::default()
+    };
+
+    let client = Client::connect_with([spawn_server(LOOPBACK_ADDRESSES[0])], &options).unwrap();
+    assert_eq!(client.socket().local_addr().unwrap(), free);
+    assert_eq!(client.request_individual_token("alice", 7).unwrap().to_string(), ALICE_SAS);
+}
+
+#[test]
+fn unknown_interfaces_are_transport_failures() {
+    let options = SocketOptions {
+        interface: Some("no-such-nic0".to_string()),
+        ..SocketOptions::default()
+    };
+
+    let error = Client::connect_with([spawn_server(LOOPBACK_ADDRESSES[0])], &options).err().unwrap();
+    assert!(matches!(error, AuthError::Io(_)), "{error:?}");
+}
+
+#[test]
+fn huge_timeouts_do_not_overflow() {
+    let policy = RetryPolicy {
+        initial_timeout: Duration::MAX,
+        ..RetryPolicy::default()
+    };
+    let client = connect(LOOPBACK_ADDRESSES[0]).with_retry_policy(policy);
+
+    assert_eq!(client.request_individual_token("alice", 7).unwrap().to_string(), ALICE_SAS);
+
+    let tokens = client.request_individual_tokens(&[("alice", 7), ("bob", 8)], 2);
+    assert_eq!(tokens[1].as_ref().unwrap().to_string(), BOB_SAS);
+}
+
+#[test]
+fn groups_too_large_for_a_datagram_are_rejected_before_sending() {
+    assert_eq!(MAX_MEMBERS, 817);
+
+    let (client, silent) = silent_client(quick_policy());
+    let alice: Sas = ALICE_SAS.parse().unwrap();
+    let members = vec![alice; MAX_MEMBERS + 1];
+
+    let error = client.request_group_token(&members).unwrap_err();
+    assert!(matches!(error, AuthError::InvalidInput(_)), "{error:?}");
+
+    let results = client.transact_all(&[Message::GroupTokenRequest(members)], 4);
+    assert!(matches!(results[0], Err(AuthError::InvalidInput(_))), "{:?}", results[0]);
+
+    silent.set_nonblocking(true).unwrap();
+    assert!(silent.recv(&mut [0; 64]).is_err());
+}
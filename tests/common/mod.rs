@@ -0,0 +1,34 @@
+#![allow(dead_code)]
+
+use std::net::SocketAddr;
+use std::thread;
+
+use udp_auth_client::server::{Server, TokenIssuer};
+
+pub const SECRET: &str = "integration-secret";
+
+pub const ALICE_SAS: &str = "alice:7:dde8b38644bb80ceed31df71a23ff57b4c215e70e893ff2ab80014e7ef60d8bd";
+pub const BOB_SAS: &str = "bob:8:cc4f7755eb91c4478297cd099318a1ef3e5c2780d6eb39bf8c2b3c4246c1edac";
+pub const GROUP_TOKEN: &str = "b4dcf620dfcfcaa0f74faf8244b3998ac7a5e0c430d11d905087f4b61d25e793";
+
+pub const LOOPBACK_ADDRESSES: [&str; 2] = ["127.0.0.1:0", "[::1]:0"];
+
+/// Starts a reference server with the test secret on a free port.
+pub fn spawn_server(address: &str) -> SocketAddr {
+    let server = Server::bind(address, TokenIssuer::new(SECRET)).expect("Failed to bind test server");
+    let local = server.local_addr().unwrap();
+
+    thread::spawn(move || server.run());
+    local
+}
+
+pub fn group_gas() -> String {
+    format!("{ALICE_SAS}+{BOB_SAS}+{GROUP_TOKEN}")
+}
+
+/// Flips the nonce of a SAS so that its token no longer matches.
+pub fn tamper(sas: &str) -> String {
+    let mut parts: Vec<String> = sas.split(':').map(String::from).collect();
+    parts[1] = (parts[1].parse::<u32>().unwrap() + 1).to_string();
+    parts.join(":")
+}
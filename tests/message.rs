@@ -0,0 +1,112 @@
+mod common;
+
+use common::{group_gas, ALICE_SAS};
+use udp_auth_client::{DecodeError, ErrorCode, Gas, Message, ParseTokenError, Sas};
+
+fn all_messages() -> Vec<Message> {
+    let sas: Sas = ALICE_SAS.parse().unwrap();
+    let gas: Gas = group_gas().parse().unwrap();
+
+    vec![
+        Message::IndividualTokenRequest { id: sas.id, nonce: sas.nonce },
+        Message::IndividualTokenResponse(sas.clone()),
+        Message::IndividualTokenValidation(sas.clone()),
+        Message::IndividualTokenStatus { sas, status: 1 },
+        Message::GroupTokenRequest(gas.members.clone()),
+        Message::GroupTokenResponse(gas.clone()),
+        Message::GroupTokenValidation(gas.clone()),
+        Message::GroupTokenStatus { gas, status: 0 },
+        Message::ErrorMessage(ErrorCode::InvalidSingleToken),
+    ]
+}
+
+#[test]
+fn every_message_round_trips_with_its_documented_length() {
+    let lengths = [18, 82, 82, 83, 164, 228, 228, 229, 4];
+
+    for (message, length) in all_messages().into_iter().zip(lengths) {
+        let bytes = message.encode();
+
+        assert_eq!(bytes.len(), length, "{message:?}");
+        assert_eq!(Message::decode(&bytes).unwrap(), message);
+    }
+}
+
+#[test]
+fn truncated_and_oversized_datagrams_are_rejected() {
+    for message in all_messages() {
+        let bytes = message.encode();
+        let expected = bytes.len();
+
+        for found in [expected - 1, expected + 1] {
+            let mut datagram = bytes.clone();
+            datagram.resize(found, 0);
+
+            assert_eq!(
+                Message::decode(&datagram),
+                Err(DecodeError::IncorrectMessageLength { expected, found }),
+                "{message:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn datagrams_shorter_than_a_type_are_rejected() {
+    assert!(matches!(Message::decode(&[]), Err(DecodeError::IncorrectMessageLength { .. })));
+    assert!(matches!(Message::decode(&[0]), Err(DecodeError::IncorrectMessageLength { .. })));
+}
+
+#[test]
+fn group_length_must_agree_with_the_n_field() {
+    let mut bytes = Message::GroupTokenResponse(group_gas().parse().unwrap()).encode();
+    bytes[3] = 3;
+
+    assert_eq!(
+        Message::decode(&bytes),
+        Err(DecodeError::IncorrectMessageLength { expected: 308, found: 228 })
+    );
+}
+
+#[test]
+fn non_ascii_fields_are_rejected() {
+    let mut bytes = Message::IndividualTokenResponse(ALICE_SAS.parse().unwrap()).encode();
+    bytes[81] = 0xff;
+
+    assert_eq!(Message::decode(&bytes), Err(DecodeError::AsciiDecodeError));
+}
+
+#[test]
+fn unknown_codes_are_rejected() {
+    assert_eq!(Message::decode(&[0, 9, 0, 0]), Err(DecodeError::InvalidMessageCode(9)));
+}
+
+#[test]
+fn unknown_error_codes_are_decoded() {
+    let message = Message::decode(&[1, 0, 0, 42]).unwrap();
+
+    assert_eq!(message, Message::ErrorMessage(ErrorCode::Unknown(42)));
+    assert_eq!(message.encode(), [1, 0, 0, 42]);
+}
+
+#[test]
+fn ids_round_trip_through_the_text_form() {
+    let alice: Sas = ALICE_SAS.parse().unwrap();
+    let token = alice.token_str();
+
+    for id in ["a", "x.y-z_w", "twelve-bytes", "a b"] {
+        let sas = Sas::new(id, 1, &token).unwrap();
+        assert_eq!(sas.to_string().parse::<Sas>().unwrap(), sas);
+
+        let gas = Gas::new(vec![sas.clone(), alice.clone()], &token).unwrap();
+        assert_eq!(gas.to_string().parse::<Gas>().unwrap(), gas);
+    }
+}
+
+#[test]
+fn ids_with_field_separators_are_rejected() {
+    let token = ALICE_SAS.parse::<Sas>().unwrap().token_str().into_owned();
+
+    assert_eq!(Sas::new("a:b", 1, &token), Err(ParseTokenError::IdSeparator(':')));
+    assert_eq!(Sas::new("a+b", 1, &token), Err(ParseTokenError::IdSeparator('+')));
+}
@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use udp_auth_client::RetryPolicy;
+
+#[test]
+fn timeout_grows_up_to_the_cap() {
+    let policy = RetryPolicy {
+        jitter: 0.0,
+        ..RetryPolicy::default()
+    };
+
+    assert_eq!(policy.timeout(0), Duration::from_secs(5));
+    assert_eq!(policy.timeout(1), Duration::from_secs(10));
+    assert_eq!(policy.timeout(2), Duration::from_secs(20));
+    assert_eq!(policy.timeout(3), Duration::from_secs(20));
+}
+
+#[test]
+fn timeout_is_capped_at_large_attempt_indices() {
+    let policy = RetryPolicy::default();
+    let jitter = policy.max_timeout.mul_f64(policy.jitter);
+
+    for attempt in [62, 64, 1000, 1100, usize::MAX] {
+        let timeout = policy.timeout(attempt);
+        assert!(timeout >= policy.max_timeout - jitter, "attempt {attempt}: {timeout:?}");
+        assert!(timeout <= policy.max_timeout + jitter, "attempt {attempt}: {timeout:?}");
+    }
+}
+
+#[test]
+fn initial_timeout_above_the_cap_is_waited_in_full() {
+    let policy = RetryPolicy {
+        initial_timeout: Duration::from_secs(30),
+        max_timeout: Duration::from_secs(20),
+        jitter: 0.0,
+        ..RetryPolicy::default()
+    };
+
+    assert_eq!(policy.timeout(0), Duration::from_secs(30));
+    assert_eq!(policy.timeout(5), Duration::from_secs(30));
+}
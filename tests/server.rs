@@ -0,0 +1,80 @@
+mod common;
+
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use common::{spawn_server, LOOPBACK_ADDRESSES};
+use udp_auth_client::{ErrorCode, Message};
+
+/// Sends a raw datagram to a fresh server and decodes its reply.
+fn exchange(datagram: &[u8]) -> Message {
+    let server = spawn_server(LOOPBACK_ADDRESSES[0]);
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    socket.connect(server).unwrap();
+
+    socket.send(datagram).unwrap();
+
+    let mut buf = vec![0; 1024];
+    let received = socket.recv(&mut buf).unwrap();
+    Message::decode(&buf[..received]).unwrap()
+}
+
+fn individual_request(id: &[u8], nonce: u32) -> Vec<u8> {
+    let mut datagram = vec![0, 1];
+    let mut id_bytes = [0u8; 12];
+    id_bytes[..id.len()].copy_from_slice(id);
+
+    datagram.extend_from_slice(&id_bytes);
+    datagram.extend_from_slice(&nonce.to_be_bytes());
+    datagram
+}
+
+#[test]
+fn unknown_type_is_an_invalid_message_code() {
+    let reply = exchange(&[0, 9, 0, 0]);
+    assert_eq!(reply, Message::ErrorMessage(ErrorCode::InvalidMessageCode));
+}
+
+#[test]
+fn response_type_is_an_invalid_message_code() {
+    let mut datagram = individual_request(b"alice", 7);
+    datagram[1] = 2;
+    datagram.resize(82, b'0');
+
+    let reply = exchange(&datagram);
+    assert_eq!(reply, Message::ErrorMessage(ErrorCode::InvalidMessageCode));
+}
+
+#[test]
+fn truncated_request_is_an_incorrect_message_length() {
+    let datagram = individual_request(b"alice", 7);
+
+    let reply = exchange(&datagram[..datagram.len() - 1]);
+    assert_eq!(reply, Message::ErrorMessage(ErrorCode::IncorrectMessageLength));
+}
+
+#[test]
+fn oversized_request_is_an_incorrect_message_length() {
+    let mut datagram = individual_request(b"alice", 7);
+    datagram.push(0);
+
+    let reply = exchange(&datagram);
+    assert_eq!(reply, Message::ErrorMessage(ErrorCode::IncorrectMessageLength));
+}
+
+#[test]
+fn non_hex_token_is_an_invalid_parameter() {
+    let mut datagram = individual_request(b"alice", 7);
+    datagram[1] = 3;
+    datagram.resize(82, b'z');
+
+    let reply = exchange(&datagram);
+    assert_eq!(reply, Message::ErrorMessage(ErrorCode::InvalidParameter));
+}
+
+#[test]
+fn non_ascii_id_is_an_ascii_decode_error() {
+    let reply = exchange(&individual_request("ação".as_bytes(), 7));
+    assert_eq!(reply, Message::ErrorMessage(ErrorCode::AsciiDecodeError));
+}